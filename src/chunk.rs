@@ -11,6 +11,7 @@ use std::io::BufReader;
 use std::io::BufWriter;
 use std::io::ErrorKind;
 use std::io::Read;
+use std::io::{Seek, SeekFrom};
 use std::io::Write;
 use std::net::{Ipv4Addr, Ipv6Addr, TcpStream};
 use std::rc::Rc;
@@ -268,6 +269,26 @@ impl<T: Read> BufReaderWithBuffer<T> {
         }
     }
 
+    /// Reads a single byte.  Returns None on error, and Some(None)
+    /// at the end of the input.
+    pub fn read_byte(&mut self) -> Option<Option<u8>> {
+        if self.buffer_index == -1 {
+            let res = self.fill_buffer();
+            if res == 0 {
+                return Some(None);
+            } else if res == -1 {
+                return None;
+            }
+        }
+        let b = self.buffer[self.buffer_index as usize];
+        self.buffer_index += 1;
+        if self.buffer_index == self.buffer_limit {
+            self.buffer_index = -1;
+            self.buffer_limit = -1;
+        }
+        Some(Some(b))
+    }
+
     pub fn readline(&mut self) -> Option<Value> {
         if self.buffer_index == -1 {
             let res = self.fill_buffer();
@@ -317,6 +338,79 @@ impl<T: Read> BufReaderWithBuffer<T> {
     }
 }
 
+/// A file reader that returns the lines of the file in reverse
+/// order, reading backwards from the end of the file in blocks.
+#[derive(Debug)]
+pub struct ReverseFileReader {
+    pub file: File,
+    /// The offset in the file from which the buffer was read.
+    pub pos: u64,
+    /// The bytes from the file that have been read but not yet
+    /// returned.
+    pub buffer: Vec<u8>,
+}
+
+impl ReverseFileReader {
+    pub fn new(mut file: File) -> std::io::Result<ReverseFileReader> {
+        let pos = file.seek(SeekFrom::End(0))?;
+        Ok(ReverseFileReader {
+            file,
+            pos,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Reads the block of the file that precedes the current
+    /// position into the start of the buffer.
+    fn fill_buffer(&mut self) -> std::io::Result<()> {
+        let n = std::cmp::min(self.pos, 8192);
+        self.pos -= n;
+        self.file.seek(SeekFrom::Start(self.pos))?;
+        let mut block = vec![0; n as usize];
+        self.file.read_exact(&mut block)?;
+        block.append(&mut self.buffer);
+        self.buffer = block;
+        Ok(())
+    }
+
+    /// Returns the last line in the file that has not yet been
+    /// returned (including its newline, if it has one), or the null
+    /// value if all lines have been returned.
+    pub fn readline(&mut self) -> Option<Value> {
+        loop {
+            /* The final byte of the buffer is ignored, since if it is
+             * a newline, then it terminates the line being returned,
+             * rather than the line before it. */
+            let len = self.buffer.len();
+            let index_opt = if len > 1 {
+                self.buffer[..len - 1].iter().rposition(|b| *b == b'\n')
+            } else {
+                None
+            };
+            match index_opt {
+                Some(index) => {
+                    let line = self.buffer.split_off(index + 1);
+                    let s = String::from_utf8_lossy(&line);
+                    return Some(new_string_value(s.to_string()));
+                }
+                None => {
+                    if self.pos == 0 {
+                        if self.buffer.is_empty() {
+                            return Some(Value::Null);
+                        }
+                        let line = std::mem::take(&mut self.buffer);
+                        let s = String::from_utf8_lossy(&line);
+                        return Some(new_string_value(s.to_string()));
+                    }
+                    if self.fill_buffer().is_err() {
+                        return None;
+                    }
+                }
+            }
+        }
+    }
+}
+
 /// A hash object paired with its current index, for use within
 /// the various hash generators.
 #[derive(Debug, Clone)]
@@ -711,6 +805,42 @@ impl CommandGenerator {
         }
     }
 
+    /// Check whether the main associated process has exited, without
+    /// blocking.  Any available output is buffered first, so that
+    /// the process does not block on writing to a full pipe.  If the
+    /// process has exited, the associated processes are cleaned up,
+    /// the exit code is stored, and true is returned.
+    pub fn try_wait(&mut self) -> bool {
+        if self.dropped {
+            return true;
+        }
+        if !self.get_bytes {
+            if !self.stdout.is_eof() {
+                let _res = self.stdout.read_available(&mut self.stdout_buffer);
+            }
+            if !self.stderr.is_eof() {
+                let _res = self.stderr.read_available(&mut self.stderr_buffer);
+            }
+        }
+        if let Some(p) = self.pid {
+            match waitpid(p, Some(WaitPidFlag::WNOHANG)) {
+                Ok(WaitStatus::StillAlive) => {
+                    return false;
+                }
+                Ok(WaitStatus::Exited(_, n)) => {
+                    self.status = Some(n);
+                }
+                Ok(WaitStatus::Signaled(_, sig, _)) => {
+                    self.status = Some(128 + sig as i32);
+                }
+                _ => {}
+            }
+            self.pid = None;
+        }
+        self.cleanup();
+        true
+    }
+
     /// Clean up the associated processes and store the exit code.
     #[allow(unused_must_use)]
     pub fn cleanup(&mut self) {
@@ -853,6 +983,8 @@ pub enum Value {
     FileReader(Rc<RefCell<BufReaderWithBuffer<File>>>),
     /// A file writer value.
     FileWriter(Rc<RefCell<BufWriter<File>>>),
+    /// A reader that returns the lines of a file in reverse order.
+    ReverseFileReader(Rc<RefCell<ReverseFileReader>>),
     /// A directory handle.
     DirectoryHandle(Rc<RefCell<ReadDir>>),
     /// A datetime with a named timezone.
@@ -967,6 +1099,9 @@ impl fmt::Debug for Value {
             Value::FileWriter(_) => {
                 write!(f, "((FileWriter))")
             }
+            Value::ReverseFileReader(_) => {
+                write!(f, "((ReverseFileReader))")
+            }
             Value::DirectoryHandle(_) => {
                 write!(f, "((DirectoryHandle))")
             }
@@ -2251,6 +2386,7 @@ impl Value {
             }
             Value::FileReader(_) => self.clone(),
             Value::FileWriter(_) => self.clone(),
+            Value::ReverseFileReader(_) => self.clone(),
             Value::DirectoryHandle(_) => self.clone(),
             Value::DateTimeNT(_) => self.clone(),
             Value::DateTimeOT(_) => self.clone(),
@@ -2299,6 +2435,7 @@ impl Value {
             (Value::EachGenerator(..), Value::EachGenerator(..)) => true,
             (Value::FileReader(..), Value::FileReader(..)) => true,
             (Value::FileWriter(..), Value::FileWriter(..)) => true,
+            (Value::ReverseFileReader(..), Value::ReverseFileReader(..)) => true,
             (Value::DirectoryHandle(..), Value::DirectoryHandle(..)) => true,
             (Value::DateTimeNT(..), Value::DateTimeNT(..)) => true,
             (Value::DateTimeOT(..), Value::DateTimeOT(..)) => true,
@@ -2329,6 +2466,7 @@ impl Value {
                 | Value::ValuesGenerator(..)
                 | Value::EachGenerator(..)
                 | Value::FileReader(..)
+                | Value::ReverseFileReader(..)
                 | Value::DirectoryHandle(..)
                 | Value::MultiGenerator(..)
                 | Value::HistoryGenerator(..)
@@ -2338,6 +2476,16 @@ impl Value {
         )
     }
 
+    pub fn is_callable(&self) -> bool {
+        matches!(
+            self,
+            Value::AnonymousFunction(_, _)
+                | Value::CoreFunction(_)
+                | Value::NamedFunction(_)
+                | Value::String(_)
+        )
+    }
+
     pub fn is_shiftable(&self) -> bool {
         if self.is_generator() {
             return true;
@@ -2374,6 +2522,7 @@ impl Value {
             Value::EachGenerator(..) => "each-gen",
             Value::FileReader(..) => "file-reader",
             Value::FileWriter(..) => "file-writer",
+            Value::ReverseFileReader(..) => "reverse-file-reader",
             Value::DirectoryHandle(..) => "dir-handle",
             Value::DateTimeNT(..) => "datetime",
             Value::DateTimeOT(..) => "datetime",
@@ -7,6 +7,8 @@
 
 extern crate ansi_term;
 extern crate atty;
+extern crate base64;
+extern crate caseless;
 extern crate chrono;
 extern crate chrono_tz;
 extern crate chronoutil;
@@ -41,7 +43,9 @@ extern crate sysinfo;
 extern crate tempfile;
 extern crate term_size;
 extern crate termion;
+extern crate unicode_normalization;
 extern crate unicode_segmentation;
+extern crate unicode_width;
 extern crate utime;
 extern crate xml;
 
@@ -13,7 +13,7 @@ use std::str;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::SystemTime;
+use std::time::{Duration, Instant, SystemTime};
 
 use indexmap::IndexMap;
 use lazy_static::lazy_static;
@@ -107,7 +107,19 @@ pub struct VM {
     /// The modification time of the nameserver file.
     dns_mtime: SystemTime,
     /// Local nameserver addresses.
-    dns_servers: Vec<ScopedIp>
+    dns_servers: Vec<ScopedIp>,
+    /// Per-function call counts and cumulative call times, keyed by
+    /// chunk address (only set while profile is running).
+    profile_data: Option<HashMap<usize, (Rc<RefCell<Chunk>>, u32, Duration)>>,
+    /// Functions registered by way of defer, with one list for each
+    /// chunk that is currently running.
+    deferred: Vec<Vec<Value>>,
+    /// The file reader for standard input, once it has been
+    /// requested by way of stdin.
+    stdin: Option<Value>,
+    /// Whether standard output should be flushed after each write.
+    /// By default, standard output is line-buffered.
+    autoflush: bool,
 }
 
 lazy_static! {
@@ -147,12 +159,22 @@ lazy_static! {
         map.insert("readline", VM::opcode_readline as fn(&mut VM) -> i32);
         map.insert("read", VM::opcode_read as fn(&mut VM) -> i32);
         map.insert("println", VM::core_println as fn(&mut VM) -> i32);
+        map.insert("flush", VM::core_flush as fn(&mut VM) -> i32);
+        map.insert("autoflush", VM::core_autoflush as fn(&mut VM) -> i32);
+        map.insert("progress", VM::core_progress as fn(&mut VM) -> i32);
+        map.insert("progress-done", VM::core_progress_done as fn(&mut VM) -> i32);
         map.insert("rm", VM::core_rm as fn(&mut VM) -> i32);
         map.insert("rmf", VM::core_rmf as fn(&mut VM) -> i32);
         map.insert("rmrf", VM::core_rmrf as fn(&mut VM) -> i32);
         map.insert("writeline", VM::core_writeline as fn(&mut VM) -> i32);
         map.insert("write", VM::core_write as fn(&mut VM) -> i32);
         map.insert("close", VM::core_close as fn(&mut VM) -> i32);
+        map.insert("with-file", VM::core_with_file as fn(&mut VM) -> i32);
+        map.insert("with-tempdir", VM::core_with_tempdir as fn(&mut VM) -> i32);
+        map.insert("tac", VM::core_tac as fn(&mut VM) -> i32);
+        map.insert("stdin", VM::core_stdin as fn(&mut VM) -> i32);
+        map.insert("stdout", VM::core_stdout as fn(&mut VM) -> i32);
+        map.insert("stderr", VM::core_stderr as fn(&mut VM) -> i32);
         map.insert("opendir", VM::core_opendir as fn(&mut VM) -> i32);
         map.insert("readdir", VM::core_readdir as fn(&mut VM) -> i32);
         map.insert("cp", VM::core_cp as fn(&mut VM) -> i32);
@@ -166,9 +188,24 @@ lazy_static! {
         map.insert("ps", VM::core_ps as fn(&mut VM) -> i32);
         map.insert("pss", VM::core_pss as fn(&mut VM) -> i32);
         map.insert("kill", VM::core_kill as fn(&mut VM) -> i32);
+        map.insert("killpg", VM::core_killpg as fn(&mut VM) -> i32);
         map.insert("m", VM::core_m as fn(&mut VM) -> i32);
         map.insert("s", VM::core_s as fn(&mut VM) -> i32);
         map.insert("c", VM::core_c as fn(&mut VM) -> i32);
+        map.insert("matches-any", VM::core_matches_any as fn(&mut VM) -> i32);
+        map.insert("starts-with-any", VM::core_starts_with_any as fn(&mut VM) -> i32);
+        map.insert("human-size", VM::core_human_size as fn(&mut VM) -> i32);
+        map.insert("parse-size", VM::core_parse_size as fn(&mut VM) -> i32);
+        map.insert("casefold", VM::core_casefold as fn(&mut VM) -> i32);
+        map.insert("slugify", VM::core_slugify as fn(&mut VM) -> i32);
+        map.insert("slugify-with", VM::core_slugify_with as fn(&mut VM) -> i32);
+        map.insert("display-width", VM::core_display_width as fn(&mut VM) -> i32);
+        map.insert("truncate", VM::core_truncate as fn(&mut VM) -> i32);
+        map.insert("truncate-with", VM::core_truncate_with as fn(&mut VM) -> i32);
+        map.insert("to-unix-newlines", VM::core_to_unix_newlines as fn(&mut VM) -> i32);
+        map.insert("to-dos-newlines", VM::core_to_dos_newlines as fn(&mut VM) -> i32);
+        map.insert("glob-match?", VM::core_glob_match as fn(&mut VM) -> i32);
+        map.insert("glob-match-ci?", VM::core_glob_match_ci as fn(&mut VM) -> i32);
         map.insert("++", VM::core_append as fn(&mut VM) -> i32);
         map.insert("push", VM::opcode_push as fn(&mut VM) -> i32);
         map.insert("unshift", VM::core_unshift as fn(&mut VM) -> i32);
@@ -181,6 +218,8 @@ lazy_static! {
         map.insert("is-r", VM::core_is_r as fn(&mut VM) -> i32);
         map.insert("is-w", VM::core_is_w as fn(&mut VM) -> i32);
         map.insert("is-x", VM::core_is_x as fn(&mut VM) -> i32);
+        map.insert("umask", VM::core_umask as fn(&mut VM) -> i32);
+        map.insert("set-umask", VM::core_set_umask as fn(&mut VM) -> i32);
         map.insert("split", VM::core_split as fn(&mut VM) -> i32);
         map.insert("splitr", VM::core_splitr as fn(&mut VM) -> i32);
         map.insert("get", VM::core_get as fn(&mut VM) -> i32);
@@ -190,6 +229,10 @@ lazy_static! {
         map.insert("each", VM::core_each as fn(&mut VM) -> i32);
         map.insert("from-json", VM::core_from_json as fn(&mut VM) -> i32);
         map.insert("to-json", VM::core_to_json as fn(&mut VM) -> i32);
+        map.insert("to-json-with", VM::core_to_json_with as fn(&mut VM) -> i32);
+        map.insert("json-validate", VM::core_json_validate as fn(&mut VM) -> i32);
+        map.insert("json-events", VM::core_json_events as fn(&mut VM) -> i32);
+        map.insert("select", VM::core_select as fn(&mut VM) -> i32);
         map.insert("from-xml", VM::core_from_xml as fn(&mut VM) -> i32);
         map.insert("to-xml", VM::core_to_xml as fn(&mut VM) -> i32);
         map.insert("from-yaml", VM::core_from_yaml as fn(&mut VM) -> i32);
@@ -214,6 +257,8 @@ lazy_static! {
         map.insert("-time", VM::core_subtime as fn(&mut VM) -> i32);
         map.insert("strptime", VM::core_strptime as fn(&mut VM) -> i32);
         map.insert("strptimez", VM::core_strptimez as fn(&mut VM) -> i32);
+        map.insert("duration-format", VM::core_duration_format as fn(&mut VM) -> i32);
+        map.insert("parse-duration", VM::core_parse_duration as fn(&mut VM) -> i32);
         map.insert("ip", VM::core_ip as fn(&mut VM) -> i32);
         map.insert("ip.from-int", VM::core_ip_from_int as fn(&mut VM) -> i32);
         map.insert("ip.addr", VM::core_ip_addr as fn(&mut VM) -> i32);
@@ -227,11 +272,28 @@ lazy_static! {
         map.insert("ip.size", VM::core_ip_size as fn(&mut VM) -> i32);
         map.insert("ip.version", VM::core_ip_version as fn(&mut VM) -> i32);
         map.insert("ip.prefixes", VM::core_ip_prefixes as fn(&mut VM) -> i32);
+        map.insert(
+            "ip.to-range-ints",
+            VM::core_ip_to_range_ints as fn(&mut VM) -> i32,
+        );
+        map.insert(
+            "ip.int-contains?",
+            VM::core_ip_int_contains as fn(&mut VM) -> i32,
+        );
+        map.insert("ip.sort", VM::core_ip_sort as fn(&mut VM) -> i32);
         map.insert("ips", VM::core_ips as fn(&mut VM) -> i32);
+        map.insert("dedup-ips", VM::core_dedup_ips as fn(&mut VM) -> i32);
         map.insert("union", VM::core_union as fn(&mut VM) -> i32);
         map.insert("isect", VM::core_isect as fn(&mut VM) -> i32);
         map.insert("diff", VM::core_diff as fn(&mut VM) -> i32);
         map.insert("symdiff", VM::core_symdiff as fn(&mut VM) -> i32);
+        map.insert("split-at", VM::core_split_at as fn(&mut VM) -> i32);
+        map.insert("position", VM::core_position as fn(&mut VM) -> i32);
+        map.insert("positions", VM::core_positions as fn(&mut VM) -> i32);
+        map.insert("head", VM::core_head as fn(&mut VM) -> i32);
+        map.insert("tail", VM::core_tail as fn(&mut VM) -> i32);
+        map.insert("shuf", VM::core_shuf as fn(&mut VM) -> i32);
+        map.insert("shuf-n", VM::core_shuf_n as fn(&mut VM) -> i32);
         map.insert("is-bool", VM::opcode_is_bool as fn(&mut VM) -> i32);
         map.insert("is-int", VM::opcode_is_int as fn(&mut VM) -> i32);
         map.insert("is-bigint", VM::opcode_is_bigint as fn(&mut VM) -> i32);
@@ -239,6 +301,7 @@ lazy_static! {
         map.insert("is-float", VM::opcode_is_flt as fn(&mut VM) -> i32);
         map.insert("is-set", VM::opcode_is_set as fn(&mut VM) -> i32);
         map.insert("is-hash", VM::opcode_is_hash as fn(&mut VM) -> i32);
+        map.insert("assert-type", VM::core_assert_type as fn(&mut VM) -> i32);
         map.insert("bigint", VM::opcode_bigint as fn(&mut VM) -> i32);
         map.insert("chr", VM::core_chr as fn(&mut VM) -> i32);
         map.insert("ord", VM::core_ord as fn(&mut VM) -> i32);
@@ -254,10 +317,25 @@ lazy_static! {
         map.insert("sqrt", VM::core_sqrt as fn(&mut VM) -> i32);
         map.insert("**", VM::core_exp as fn(&mut VM) -> i32);
         map.insert("abs", VM::core_abs as fn(&mut VM) -> i32);
+        map.insert("round-to", VM::core_round_to as fn(&mut VM) -> i32);
+        map.insert("floor-to", VM::core_floor_to as fn(&mut VM) -> i32);
+        map.insert("ceil-to", VM::core_ceil_to as fn(&mut VM) -> i32);
         map.insert("delete", VM::core_delete as fn(&mut VM) -> i32);
         map.insert("exists", VM::core_exists as fn(&mut VM) -> i32);
+        map.insert("contains?", VM::core_contains as fn(&mut VM) -> i32);
+        map.insert("top", VM::core_top as fn(&mut VM) -> i32);
+        map.insert("pivot", VM::core_pivot as fn(&mut VM) -> i32);
+        map.insert("join-on", VM::core_join_on as fn(&mut VM) -> i32);
+        map.insert("left-join-on", VM::core_left_join_on as fn(&mut VM) -> i32);
+        map.insert("outer-join-on", VM::core_outer_join_on as fn(&mut VM) -> i32);
+        map.insert("flatten-hash", VM::core_flatten_hash as fn(&mut VM) -> i32);
+        map.insert("unflatten-hash", VM::core_unflatten_hash as fn(&mut VM) -> i32);
         map.insert("chmod", VM::core_chmod as fn(&mut VM) -> i32);
         map.insert("chown", VM::core_chown as fn(&mut VM) -> i32);
+        map.insert("uid-name", VM::core_uid_name as fn(&mut VM) -> i32);
+        map.insert("gid-name", VM::core_gid_name as fn(&mut VM) -> i32);
+        map.insert("name-uid", VM::core_name_uid as fn(&mut VM) -> i32);
+        map.insert("name-gid", VM::core_name_gid as fn(&mut VM) -> i32);
         map.insert("mkdir", VM::core_mkdir as fn(&mut VM) -> i32);
         map.insert("rmdir", VM::core_rmdir as fn(&mut VM) -> i32);
         map.insert("link", VM::core_link as fn(&mut VM) -> i32);
@@ -265,14 +343,32 @@ lazy_static! {
         map.insert("env", VM::core_env as fn(&mut VM) -> i32);
         map.insert("getenv", VM::core_getenv as fn(&mut VM) -> i32);
         map.insert("setenv", VM::core_setenv as fn(&mut VM) -> i32);
+        map.insert("path-split", VM::core_path_split as fn(&mut VM) -> i32);
+        map.insert("path-join", VM::core_path_join as fn(&mut VM) -> i32);
+        map.insert("path-prepend", VM::core_path_prepend as fn(&mut VM) -> i32);
+        map.insert("path-append", VM::core_path_append as fn(&mut VM) -> i32);
+        map.insert("load-env", VM::core_load_env as fn(&mut VM) -> i32);
         map.insert("md5", VM::core_md5 as fn(&mut VM) -> i32);
         map.insert("sha1", VM::core_sha1 as fn(&mut VM) -> i32);
         map.insert("sha256", VM::core_sha256 as fn(&mut VM) -> i32);
         map.insert("sha512", VM::core_sha512 as fn(&mut VM) -> i32);
+        map.insert("fingerprint", VM::core_fingerprint as fn(&mut VM) -> i32);
         map.insert("sort", VM::core_sort as fn(&mut VM) -> i32);
         map.insert("sortp", VM::core_sortp as fn(&mut VM) -> i32);
         map.insert("fmt", VM::core_fmt as fn(&mut VM) -> i32);
         map.insert("fmtq", VM::core_fmtq as fn(&mut VM) -> i32);
+        map.insert("to-bytes", VM::core_to_bytes as fn(&mut VM) -> i32);
+        map.insert("from-bytes", VM::core_from_bytes as fn(&mut VM) -> i32);
+        map.insert("to-base64", VM::core_to_base64 as fn(&mut VM) -> i32);
+        map.insert("from-base64", VM::core_from_base64 as fn(&mut VM) -> i32);
+        map.insert("to-base64url", VM::core_to_base64url as fn(&mut VM) -> i32);
+        map.insert("from-base64url", VM::core_from_base64url as fn(&mut VM) -> i32);
+        map.insert("ascii?", VM::core_is_ascii as fn(&mut VM) -> i32);
+        map.insert("valid-utf8?", VM::core_is_valid_utf8 as fn(&mut VM) -> i32);
+        map.insert("uri-encode", VM::core_uri_encode as fn(&mut VM) -> i32);
+        map.insert("uri-decode", VM::core_uri_decode as fn(&mut VM) -> i32);
+        map.insert("ordinal", VM::core_ordinal as fn(&mut VM) -> i32);
+        map.insert("number-words", VM::core_number_words as fn(&mut VM) -> i32);
         map.insert("exec", VM::core_exec as fn(&mut VM) -> i32);
         map.insert("cmd", VM::core_cmd as fn(&mut VM) -> i32);
         map.insert("cmd/e", VM::core_cmde as fn(&mut VM) -> i32);
@@ -297,12 +393,24 @@ lazy_static! {
         map.insert("nc", VM::core_nc as fn(&mut VM) -> i32);
         map.insert("exit", VM::core_exit as fn(&mut VM) -> i32);
         map.insert(".ss", VM::core_printstacksingle as fn(&mut VM) -> i32);
+        map.insert(".d", VM::core_debug_stack as fn(&mut VM) -> i32);
+        map.insert("debug-stack", VM::core_debug_stack as fn(&mut VM) -> i32);
         map.insert("jobs", VM::core_jobs as fn(&mut VM) -> i32);
         map.insert("status", VM::core_status as fn(&mut VM) -> i32);
+        map.insert("wait", VM::core_wait as fn(&mut VM) -> i32);
+        map.insert("wait-all", VM::core_wait_all as fn(&mut VM) -> i32);
         map.insert("source", VM::core_source as fn(&mut VM) -> i32);
         map.insert("dnsat", VM::core_dnsat as fn(&mut VM) -> i32);
         map.insert("dns", VM::core_dns as fn(&mut VM) -> i32);
         map.insert("readlink", VM::core_readlink as fn(&mut VM) -> i32);
+        map.insert("is-tty", VM::core_is_tty as fn(&mut VM) -> i32);
+        map.insert("term-size", VM::core_term_size as fn(&mut VM) -> i32);
+        map.insert("hostname", VM::core_hostname as fn(&mut VM) -> i32);
+        map.insert("uname", VM::core_uname as fn(&mut VM) -> i32);
+        map.insert("loadavg", VM::core_loadavg as fn(&mut VM) -> i32);
+        map.insert("meminfo", VM::core_meminfo as fn(&mut VM) -> i32);
+        map.insert("profile", VM::core_profile as fn(&mut VM) -> i32);
+        map.insert("defer", VM::core_defer as fn(&mut VM) -> i32);
         map
     };
 
@@ -332,6 +440,10 @@ lazy_static! {
         set.insert("max");
         set.insert("chomp");
         set.insert("grep");
+        set.insert("reject");
+        set.insert("paste");
+        set.insert("comm");
+        set.insert("progress-gen");
         set.insert("min");
         set.insert("grep-generator");
         set.insert("nip");
@@ -357,6 +469,9 @@ lazy_static! {
         set.insert("before");
         set.insert("pairwise");
         set.insert("slide");
+        set.insert("chunk-while");
+        set.insert("number-lines-with");
+        set.insert("number-lines");
         set.insert("id");
         set
     };
@@ -452,7 +567,11 @@ impl VM {
             child_processes: IndexMap::new(),
             dns_mtime: std::fs::metadata("/etc/resolv.conf").unwrap()
                                                             .modified().unwrap(),
-            dns_servers: config.nameservers
+            dns_servers: config.nameservers,
+            profile_data: None,
+            deferred: Vec::new(),
+            stdin: None,
+            autoflush: false,
         }
     }
 
@@ -1089,6 +1208,9 @@ impl VM {
         plvs_stack: Option<Rc<RefCell<Vec<Value>>>>,
         call_chunk: Rc<RefCell<Chunk>>,
     ) -> bool {
+        if self.profile_data.is_some() {
+            return self.call_named_function_profiled(plvs_stack, call_chunk);
+        }
         if call_chunk.borrow().is_generator {
             self.call_generator(call_chunk)
         } else {
@@ -1096,6 +1218,97 @@ impl VM {
         }
     }
 
+    /// Call a named function, recording the call and the time spent
+    /// in the call in the profile data.
+    fn call_named_function_profiled(
+        &mut self,
+        plvs_stack: Option<Rc<RefCell<Vec<Value>>>>,
+        call_chunk: Rc<RefCell<Chunk>>,
+    ) -> bool {
+        let start = Instant::now();
+        let res = if call_chunk.borrow().is_generator {
+            self.call_generator(call_chunk.clone())
+        } else {
+            self.call_non_generator(plvs_stack, call_chunk.clone())
+        };
+        let elapsed = start.elapsed();
+        if let Some(profile_data) = self.profile_data.as_mut() {
+            let key = Rc::as_ptr(&call_chunk) as usize;
+            let entry = profile_data
+                .entry(key)
+                .or_insert((call_chunk, 0, Duration::ZERO));
+            entry.1 += 1;
+            entry.2 += elapsed;
+        }
+        res
+    }
+
+    /// Takes a function map and a map from chunk address to function
+    /// name, and adds the names of each of the functions in the
+    /// function map (including nested functions) to the name map.
+    fn collect_function_names(functions: &HashMap<String, Rc<RefCell<Chunk>>>,
+                              names: &mut HashMap<usize, String>) {
+        for (name, chunk) in functions.iter() {
+            let key = Rc::as_ptr(chunk) as usize;
+            if names.contains_key(&key) {
+                continue;
+            }
+            let name = if name.starts_with("anon") {
+                "(anonymous)".to_string()
+            } else {
+                name.clone()
+            };
+            names.insert(key, name);
+            VM::collect_function_names(&chunk.borrow().functions, names);
+        }
+    }
+
+    /// Takes a callable, runs it, and prints to standard error the
+    /// number of times that each named function was called while it
+    /// was running, along with the cumulative time spent in each of
+    /// those functions.
+    pub fn core_profile(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("profile requires one argument");
+            return 0;
+        }
+
+        let fn_rr = self.stack.pop().unwrap();
+        if self.profile_data.is_some() {
+            /* Already profiling, so just call the function. */
+            return if self.call(OpCode::Call, fn_rr) { 1 } else { 0 };
+        }
+
+        self.profile_data = Some(HashMap::new());
+        let start = Instant::now();
+        let res = self.call(OpCode::Call, fn_rr);
+        let total = start.elapsed();
+        let profile_data = self.profile_data.take().unwrap();
+
+        let mut names = HashMap::new();
+        VM::collect_function_names(&self.global_functions.borrow(), &mut names);
+        VM::collect_function_names(&self.chunk.borrow().functions, &mut names);
+        for (chunk, _) in self.call_stack_chunks.iter() {
+            VM::collect_function_names(&chunk.borrow().functions, &mut names);
+        }
+
+        let mut entries: Vec<(String, u32, Duration)> =
+            profile_data.iter().map(|(key, (_, count, time))| {
+                let name = names.get(key).cloned()
+                                .unwrap_or_else(|| "(unknown)".to_string());
+                (name, *count, *time)
+            }).collect();
+        entries.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)));
+
+        eprintln!("{:>8}  {:>10}  function", "calls", "seconds");
+        for (name, count, time) in entries {
+            eprintln!("{:>8}  {:>10.6}  {}", count, time.as_secs_f64(), name);
+        }
+        eprintln!("{:>8}  {:>10.6}  (total)", "", total.as_secs_f64());
+
+        if res { 1 } else { 0 }
+    }
+
     /// Convert a string into a callable object, by looking at the
     /// built-in forms and the current functions that are in scope.
     pub fn string_to_callable(&mut self, s: &str) -> Option<Value> {
@@ -1274,8 +1487,10 @@ impl VM {
         self.call_stack_chunks.push((self.chunk.clone(), self.i));
         self.chunk = chunk;
         self.i = 0;
+        self.deferred.push(Vec::new());
         let res = self.run_inner();
-        if res == 0 {
+        let deferred_res = self.run_deferred();
+        if res == 0 || !deferred_res {
             return 0;
         }
         let mp = self.call_stack_chunks.pop().unwrap();
@@ -1285,6 +1500,52 @@ impl VM {
         res
     }
 
+    /// Calls the functions registered by way of defer for the
+    /// current chunk, in reverse order of registration.  This happens
+    /// regardless of whether the chunk completed successfully.  All
+    /// of the functions are called, even if one of them fails, and
+    /// the return value indicates whether all of them succeeded.
+    fn run_deferred(&mut self) -> bool {
+        let deferred = self.deferred.pop().unwrap_or_default();
+        let mut success = true;
+        for function_rr in deferred.into_iter().rev() {
+            if !self.call(OpCode::Call, function_rr) {
+                success = false;
+            }
+        }
+        success
+    }
+
+    /// Takes a callable value as its single argument.  Registers the
+    /// callable so that it is called when the current function
+    /// returns, including when it returns due to an error.
+    pub fn core_defer(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("defer requires one argument");
+            return 0;
+        }
+
+        let function_rr = self.stack.pop().unwrap();
+        if !function_rr.is_callable() {
+            self.print_error("defer argument must be callable");
+            return 0;
+        }
+        if self.chunk.borrow().is_generator {
+            self.print_error("defer cannot be used in a generator");
+            return 0;
+        }
+        match self.deferred.last_mut() {
+            Some(deferred) => {
+                deferred.push(function_rr);
+                1
+            }
+            _ => {
+                self.print_error("defer must be called from within a function");
+                0
+            }
+        }
+    }
+
     /// Takes the global functions, the call stack chunks, the current
     /// chunk, the values for the current chunk, the instruction
     /// index, the global variables for the current generator (if
@@ -2194,7 +2455,11 @@ impl VM {
 
                     let b = value_rr.to_bool();
                     if !b {
-                        i -= jmp_len;
+                        /* If the loop starts at the first instruction
+                         * in the chunk, then this wraps around, and
+                         * the increment at the end of the main loop
+                         * brings the index back to zero. */
+                        i = i.wrapping_sub(jmp_len);
                         if self.debug {
                             eprintln!("  > Jumping in reverse by {} to {}",
                                       jmp_len, i);
@@ -2225,7 +2490,8 @@ impl VM {
                         match value_rr {
                             Value::Int(n2) => {
                                 if cmp_rr != *n2 {
-                                    i -= jmp_len;
+                                    /* See JumpNeR. */
+                                    i = i.wrapping_sub(jmp_len);
                                     if self.debug {
                                         eprintln!("  > Jumping in reverse by {} to {}",
                                                   jmp_len, i);
@@ -2273,7 +2539,8 @@ impl VM {
                     i += 1;
                     let i2: usize = chunk.borrow().data[i].try_into().unwrap();
                     let jmp_len: usize = (i1 << 8) | i2;
-                    i -= jmp_len;
+                    /* See JumpNeR. */
+                    i = i.wrapping_sub(jmp_len);
                     if self.debug {
                         eprintln!("  > Jumping in reverse by {} to {}",
                                   jmp_len, i);
@@ -2333,7 +2600,9 @@ impl VM {
                     std::process::abort();
                 }
             }
-            i += 1;
+            /* Wrapping, because a reverse jump to the start of the
+             * chunk leaves the index at usize::MAX. */
+            i = i.wrapping_add(1);
         }
 
         if list_count > 0 {
@@ -2,7 +2,7 @@ use num::FromPrimitive;
 use num::ToPrimitive;
 use num::Integer;
 use num_bigint::BigInt;
-use num_traits::Signed;
+use num_traits::{Signed, Zero};
 
 use crate::chunk::Value;
 use crate::vm::*;
@@ -79,6 +79,23 @@ fn remainder_ints(n1: i32, n2: i32) -> Value {
     }
 }
 
+/// The rounding modes used by round-to, floor-to, and ceil-to.
+#[derive(Clone, Copy)]
+enum RoundMode {
+    Nearest,
+    Floor,
+    Ceil,
+}
+
+/// Convert a bigint into a value, using an integer value if the
+/// bigint fits within an i32.
+fn bigint_to_value(n: BigInt) -> Value {
+    match n.to_i32() {
+        Some(i) => Value::Int(i),
+        None => Value::BigInt(n),
+    }
+}
+
 impl VM {
     /// Helper function for adding two values together and placing the
     /// result onto the stack.  Returns an integer indicating whether
@@ -1524,4 +1541,99 @@ impl VM {
 
         1
     }
+
+    /// Helper function for round-to, floor-to, and ceil-to.  Takes a
+    /// number and a multiple as its arguments, and puts the number
+    /// rounded to a multiple of that value onto the stack.  If either
+    /// argument is a float, then the result is a float.  Ties are
+    /// rounded away from zero.
+    fn round_to_inner(&mut self, name: &str, mode: RoundMode) -> i32 {
+        if self.stack.len() < 2 {
+            let err_str = format!("{} requires two arguments", name);
+            self.print_error(&err_str);
+            return 0;
+        }
+
+        let multiple_rr = self.stack.pop().unwrap();
+        let number_rr = self.stack.pop().unwrap();
+
+        let ints = match (&number_rr, &multiple_rr) {
+            (Value::Float(_), _) | (_, Value::Float(_)) => None,
+            _ => match (number_rr.to_bigint(), multiple_rr.to_bigint()) {
+                (Some(n), Some(m)) => Some((n, m.abs())),
+                _ => None,
+            },
+        };
+        let (number, multiple) = match ints {
+            Some(ints) => ints,
+            _ => {
+                let number = match number_rr.to_float() {
+                    Some(n) => n,
+                    _ => {
+                        let err_str = format!("first {} argument must be number", name);
+                        self.print_error(&err_str);
+                        return 0;
+                    }
+                };
+                let multiple = match multiple_rr.to_float() {
+                    Some(m) => m.abs(),
+                    _ => {
+                        let err_str = format!("second {} argument must be number", name);
+                        self.print_error(&err_str);
+                        return 0;
+                    }
+                };
+                if multiple == 0.0 {
+                    let err_str = format!("{} multiple cannot be zero", name);
+                    self.print_error(&err_str);
+                    return 0;
+                }
+                let quotient = number / multiple;
+                let rounded = match mode {
+                    RoundMode::Nearest => quotient.round(),
+                    RoundMode::Floor => quotient.floor(),
+                    RoundMode::Ceil => quotient.ceil(),
+                };
+                self.stack.push(Value::Float(rounded * multiple));
+                return 1;
+            }
+        };
+        if multiple.is_zero() {
+            let err_str = format!("{} multiple cannot be zero", name);
+            self.print_error(&err_str);
+            return 0;
+        }
+        let (quotient, remainder) = number.div_mod_floor(&multiple);
+        let floor = &quotient * &multiple;
+        let round_up = match mode {
+            RoundMode::Floor => false,
+            RoundMode::Ceil => !remainder.is_zero(),
+            RoundMode::Nearest => {
+                let doubled = &remainder * 2;
+                doubled > multiple
+                    || (doubled == multiple && !number.is_negative())
+            }
+        };
+        let result = if round_up { floor + multiple } else { floor };
+        self.stack.push(bigint_to_value(result));
+        1
+    }
+
+    /// Round the first argument to the nearest multiple of the second
+    /// argument.
+    pub fn core_round_to(&mut self) -> i32 {
+        self.round_to_inner("round-to", RoundMode::Nearest)
+    }
+
+    /// Round the first argument down to a multiple of the second
+    /// argument.
+    pub fn core_floor_to(&mut self) -> i32 {
+        self.round_to_inner("floor-to", RoundMode::Floor)
+    }
+
+    /// Round the first argument up to a multiple of the second
+    /// argument.
+    pub fn core_ceil_to(&mut self) -> i32 {
+        self.round_to_inner("ceil-to", RoundMode::Ceil)
+    }
 }
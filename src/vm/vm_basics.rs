@@ -246,14 +246,7 @@ impl VM {
         }
 
         let i1_rr = self.stack.pop().unwrap();
-        let is_callable = matches!(
-            i1_rr,
-            Value::AnonymousFunction(_, _)
-                | Value::CoreFunction(_)
-                | Value::NamedFunction(_)
-                | Value::String(_)
-        );
-        self.stack.push(Value::Bool(is_callable));
+        self.stack.push(Value::Bool(i1_rr.is_callable()));
         1
     }
 
@@ -573,6 +566,65 @@ impl VM {
         1
     }
 
+    /// Check that a value has one of the specified types, raising an
+    /// error if it does not.  The value is left on the stack.
+    pub fn core_assert_type(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("assert-type requires two arguments");
+            return 0;
+        }
+
+        let types_rr = self.stack.pop().unwrap();
+        let mut types = Vec::new();
+        match types_rr {
+            Value::List(ref lst) => {
+                for e in lst.borrow().iter() {
+                    let e_opt: Option<&str>;
+                    to_str!(e, e_opt);
+                    match e_opt {
+                        Some(s) => {
+                            types.push(s.to_string());
+                        }
+                        _ => {
+                            self.print_error("assert-type type list must contain strings");
+                            return 0;
+                        }
+                    }
+                }
+                if types.is_empty() {
+                    self.print_error("assert-type type list must not be empty");
+                    return 0;
+                }
+            }
+            _ => {
+                let types_opt: Option<&str>;
+                to_str!(types_rr, types_opt);
+                match types_opt {
+                    Some(s) => {
+                        types.push(s.to_string());
+                    }
+                    _ => {
+                        self.print_error("assert-type type argument must be string or list");
+                        return 0;
+                    }
+                }
+            }
+        }
+
+        let value_rr = self.stack.last().unwrap();
+        let actual = value_rr.type_string();
+        if !types.contains(&actual) {
+            let err = format!(
+                "assert-type: expected {}, got {}",
+                types.join(" or "),
+                actual
+            );
+            self.print_error(&err);
+            return 0;
+        }
+        1
+    }
+
     /// Get a random floating-point value.
     pub fn opcode_rand(&mut self) -> i32 {
         if self.stack.is_empty() {
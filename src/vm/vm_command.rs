@@ -7,9 +7,13 @@ use std::io::Write;
 use std::os::fd::FromRawFd;
 use std::rc::Rc;
 use std::str;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time;
 
 use lazy_static::lazy_static;
-use nix::unistd::{fork, ForkResult};
+use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+use nix::unistd::{fork, ForkResult, Pid};
 use nonblock::NonBlockingReader;
 use regex::Regex;
 use std::process::{Command, Stdio};
@@ -590,6 +594,98 @@ impl VM {
         }
     }
 
+    /// Waits for the given process to exit, and returns its exit
+    /// status.  Returns None if the wait is interrupted or fails.
+    fn wait_for_pid(&mut self, pid: Pid) -> Option<i32> {
+        loop {
+            match waitpid(pid, Some(WaitPidFlag::WNOHANG)) {
+                Ok(WaitStatus::StillAlive) => {}
+                Ok(WaitStatus::Exited(_, n)) => {
+                    return Some(n);
+                }
+                Ok(WaitStatus::Signaled(_, sig, _)) => {
+                    return Some(128 + sig as i32);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    let err_str = format!("unable to wait for process {}: {}", pid, e);
+                    self.print_error(&err_str);
+                    return None;
+                }
+            }
+            if !self.running.load(Ordering::SeqCst) {
+                self.running.store(true, Ordering::SeqCst);
+                self.stack.clear();
+                return None;
+            }
+            thread::sleep(time::Duration::from_millis(10));
+        }
+    }
+
+    /// Takes a command generator or a process ID as its single
+    /// argument.  Waits for the associated process to exit, and
+    /// returns its exit status.  (If the process was terminated by a
+    /// signal, the exit status is 128 plus the signal number.)
+    pub fn core_wait(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("wait requires one argument");
+            return 0;
+        }
+
+        let value_rr = self.stack.pop().unwrap();
+        match value_rr {
+            Value::CommandGenerator(ref cg) => {
+                loop {
+                    if cg.borrow_mut().try_wait() {
+                        break;
+                    }
+                    if !self.running.load(Ordering::SeqCst) {
+                        self.running.store(true, Ordering::SeqCst);
+                        self.stack.clear();
+                        return 0;
+                    }
+                    thread::sleep(time::Duration::from_millis(10));
+                }
+                self.stack.push(cg.borrow().status());
+                1
+            }
+            _ => match value_rr.to_int() {
+                Some(pid) if pid > 0 => {
+                    match self.wait_for_pid(Pid::from_raw(pid)) {
+                        Some(status) => {
+                            self.child_processes.shift_remove(&(pid as u32));
+                            self.stack.push(Value::Int(status));
+                            1
+                        }
+                        _ => 0,
+                    }
+                }
+                _ => {
+                    self.print_error("wait argument must be command generator or process ID");
+                    0
+                }
+            },
+        }
+    }
+
+    /// Waits for all of the background processes (as per jobs) to
+    /// exit.
+    pub fn core_wait_all(&mut self) -> i32 {
+        let pids: Vec<u32> = self.child_processes.keys().cloned().collect();
+        for pid in pids {
+            /* If the process has already exited, or has been reaped
+             * elsewhere, then there is no need to wait for it. */
+            let res = waitpid(Pid::from_raw(pid as i32), Some(WaitPidFlag::WNOHANG));
+            if let Ok(WaitStatus::StillAlive) = res {
+                if self.wait_for_pid(Pid::from_raw(pid as i32)).is_none() {
+                    return 0;
+                }
+            }
+            self.child_processes.shift_remove(&pid);
+        }
+        1
+    }
+
     /// Takes a string as its single argument, and runs the string as
     /// a command (uncaptured).
     pub fn core_exec(&mut self) -> i32 {
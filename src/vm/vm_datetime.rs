@@ -5,9 +5,18 @@ use std::str::FromStr;
 use chrono::format::{parse, Parsed, StrftimeItems};
 use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
 use chronoutil::RelativeDuration;
+use lazy_static::lazy_static;
+use regex::Regex;
 
 use crate::vm::*;
 
+lazy_static! {
+    static ref DURATION: Regex =
+        Regex::new("^[+-]?(\\d+(\\.\\d+)?(ms|d|h|m|s))+$").unwrap();
+    static ref DURATION_PART: Regex =
+        Regex::new("(\\d+(?:\\.\\d+)?)(ms|d|h|m|s)").unwrap();
+}
+
 impl VM {
     /// Returns the current time as a date-time object, offset at UTC.
     pub fn core_now(&mut self) -> i32 {
@@ -454,4 +463,93 @@ impl VM {
             }
         }
     }
+
+    /// Takes a number of seconds as its single argument.  Returns a
+    /// string describing that duration (e.g. "1h23m45s").
+    pub fn core_duration_format(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("duration-format requires one argument");
+            return 0;
+        }
+
+        let secs_rr = self.stack.pop().unwrap();
+        let secs = match secs_rr.to_float() {
+            Some(n) if n.is_finite() => n,
+            _ => {
+                self.print_error("duration-format argument must be number");
+                return 0;
+            }
+        };
+
+        let mut result = String::new();
+        let nanos = (secs.abs() * 1e9).round() as u128;
+        if secs < 0.0 && nanos != 0 {
+            result.push('-');
+        }
+        let mut whole = nanos / 1_000_000_000;
+        let fraction = nanos % 1_000_000_000;
+        for (suffix, length) in [("d", 86400), ("h", 3600), ("m", 60)] {
+            if whole >= length {
+                write!(result, "{}{}", whole / length, suffix).unwrap();
+                whole %= length;
+            }
+        }
+        if fraction != 0 {
+            let fraction_str = format!("{:09}", fraction);
+            let fraction_str = fraction_str.trim_end_matches('0');
+            write!(result, "{}.{}s", whole, fraction_str).unwrap();
+        } else if whole != 0 || nanos == 0 {
+            write!(result, "{}s", whole).unwrap();
+        }
+        self.stack.push(new_string_value(result));
+        1
+    }
+
+    /// Takes a duration string (e.g. "1h30m" or "90s") as its single
+    /// argument.  Returns the number of seconds in that duration.
+    /// The result is a float if the duration has a fractional
+    /// number of seconds, and an integer otherwise.
+    pub fn core_parse_duration(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("parse-duration requires one argument");
+            return 0;
+        }
+
+        let duration_rr = self.stack.pop().unwrap();
+        let duration_opt: Option<&str>;
+        to_str!(duration_rr, duration_opt);
+        let duration_str = match duration_opt {
+            Some(s) => s.trim(),
+            _ => {
+                self.print_error("parse-duration argument must be string");
+                return 0;
+            }
+        };
+        if !DURATION.is_match(duration_str) {
+            self.print_error("parse-duration argument is not a valid duration");
+            return 0;
+        }
+
+        let mut secs = 0.0;
+        for captures in DURATION_PART.captures_iter(duration_str) {
+            let n = captures.get(1).unwrap().as_str().parse::<f64>().unwrap();
+            let length = match captures.get(2).unwrap().as_str() {
+                "d" => 86400.0,
+                "h" => 3600.0,
+                "m" => 60.0,
+                "s" => 1.0,
+                _ => 0.001,
+            };
+            secs += n * length;
+        }
+        if duration_str.starts_with('-') {
+            secs = -secs;
+        }
+        if secs.fract() == 0.0 && secs.abs() <= i32::MAX as f64 {
+            self.stack.push(Value::Int(secs as i32));
+        } else {
+            self.stack.push(Value::Float(secs));
+        }
+        1
+    }
 }
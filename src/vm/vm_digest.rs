@@ -4,6 +4,70 @@ use sha2::{Sha256, Sha512};
 use crate::chunk::Value;
 use crate::vm::*;
 
+/// Adds a length-prefixed string to the fingerprint hasher.
+fn fingerprint_str(hasher: &mut Sha256, tag: &str, s: &str) {
+    hasher.update(format!("{}{}:", tag, s.len()).as_bytes());
+    hasher.update(s.as_bytes());
+}
+
+/// Adds the canonical representation of the value to the fingerprint
+/// hasher.  Numeric values that are equal (e.g. an int and a bigint)
+/// have the same representation, and hash and set entries are
+/// processed in key order, so that structurally equal values produce
+/// the same fingerprint.  Returns false if the value (or a value that
+/// it contains) cannot be fingerprinted.
+fn fingerprint_value(hasher: &mut Sha256, value: &Value) -> bool {
+    match value {
+        Value::Null => hasher.update(b"n"),
+        Value::Bool(b) => hasher.update(if *b { b"t" } else { b"f" }),
+        Value::Byte(n) => fingerprint_str(hasher, "i", &n.to_string()),
+        Value::Int(n) => fingerprint_str(hasher, "i", &n.to_string()),
+        Value::BigInt(n) => fingerprint_str(hasher, "i", &n.to_string()),
+        Value::Float(f) => {
+            let f = if *f == 0.0 { 0.0 } else { *f };
+            hasher.update(format!("d{:016x}", f.to_bits()).as_bytes());
+        }
+        Value::String(st) => fingerprint_str(hasher, "s", &st.borrow().string),
+        Value::Command(s, _) => fingerprint_str(hasher, "c", s),
+        Value::CommandUncaptured(s) => fingerprint_str(hasher, "u", s),
+        Value::DateTimeNT(d) => {
+            fingerprint_str(hasher, "z", d.timezone().name());
+            fingerprint_str(hasher, "a", &d.to_rfc3339());
+        }
+        Value::DateTimeOT(d) => fingerprint_str(hasher, "o", &d.to_rfc3339()),
+        Value::Ipv4(_) | Value::Ipv6(_) | Value::Ipv4Range(_)
+                | Value::Ipv6Range(_) | Value::IpSet(_) => {
+            fingerprint_str(hasher, "p", &value.to_string().unwrap());
+        }
+        Value::List(lst) => {
+            let lst = lst.borrow();
+            hasher.update(format!("l{}:", lst.len()).as_bytes());
+            for e in lst.iter() {
+                if !fingerprint_value(hasher, e) {
+                    return false;
+                }
+            }
+        }
+        Value::Hash(map) | Value::Set(map) => {
+            let map = map.borrow();
+            let tag = if let Value::Hash(_) = value { "h" } else { "e" };
+            hasher.update(format!("{}{}:", tag, map.len()).as_bytes());
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            for k in keys {
+                if let Value::Hash(_) = value {
+                    fingerprint_str(hasher, "k", k);
+                }
+                if !fingerprint_value(hasher, map.get(k).unwrap()) {
+                    return false;
+                }
+            }
+        }
+        _ => return false,
+    }
+    true
+}
+
 impl VM {
     /// Takes a string as its single argument.  Hashes the string
     /// using the MD5 algorithm and adds the result to the stack.
@@ -126,4 +190,25 @@ impl VM {
         }
         1
     }
+
+    /// Takes a value as its single argument.  Puts a hexadecimal
+    /// string that fingerprints the value onto the stack.
+    /// Structurally equal values have the same fingerprint.
+    pub fn core_fingerprint(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("fingerprint requires one argument");
+            return 0;
+        }
+
+        let value_rr = self.stack.pop().unwrap();
+        let mut hasher = Sha256::new();
+        if !fingerprint_value(&mut hasher, &value_rr) {
+            self.print_error("fingerprint argument cannot be fingerprinted");
+            return 0;
+        }
+        let digest = hasher.finalize();
+        let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+        self.stack.push(new_string_value(hex));
+        1
+    }
 }
@@ -1,5 +1,8 @@
 use std::cell::RefCell;
+use std::collections::VecDeque;
 use std::env;
+use std::fs;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use indexmap::IndexMap;
@@ -93,4 +96,278 @@ impl VM {
 
         1
     }
+
+    /// Takes a PATH-like string as its argument.  Splits the string
+    /// using the platform's path separator, and puts the resulting
+    /// list of components onto the stack.
+    pub fn core_path_split(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("path-split requires one argument");
+            return 0;
+        }
+
+        let value_rr = self.stack.pop().unwrap();
+        let value_opt: Option<&str>;
+        to_str!(value_rr, value_opt);
+        match value_opt {
+            Some(s) => {
+                let lst = env::split_paths(s)
+                    .map(|p| new_string_value(p.to_string_lossy().to_string()))
+                    .collect::<VecDeque<Value>>();
+                self.stack.push(Value::List(Rc::new(RefCell::new(lst))));
+                1
+            }
+            _ => {
+                self.print_error("path-split argument must be a string");
+                0
+            }
+        }
+    }
+
+    /// Helper function for joining path components.  Returns the
+    /// joined string, or None (after printing an error) if the
+    /// components cannot be joined.
+    fn join_path_components(&self, name: &str, components: &[String]) -> Option<String> {
+        match env::join_paths(components) {
+            Ok(s) => Some(s.to_string_lossy().to_string()),
+            Err(e) => {
+                let err_str = format!("{} unable to join paths: {}", name, e);
+                self.print_error(&err_str);
+                None
+            }
+        }
+    }
+
+    /// Takes a list of path components as its argument.  Joins the
+    /// components using the platform's path separator, and puts the
+    /// resulting string onto the stack.
+    pub fn core_path_join(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("path-join requires one argument");
+            return 0;
+        }
+
+        let lst_rr = self.stack.pop().unwrap();
+        let mut components = Vec::new();
+        match lst_rr {
+            Value::List(lst) => {
+                for e in lst.borrow().iter() {
+                    let e_opt: Option<&str>;
+                    to_str!(e, e_opt);
+                    match e_opt {
+                        Some(s) => components.push(s.to_string()),
+                        _ => {
+                            self.print_error("path-join list must contain strings");
+                            return 0;
+                        }
+                    }
+                }
+            }
+            _ => {
+                self.print_error("path-join argument must be a list");
+                return 0;
+            }
+        }
+
+        match self.join_path_components("path-join", &components) {
+            Some(s) => {
+                self.stack.push(new_string_value(s));
+                1
+            }
+            _ => 0,
+        }
+    }
+
+    /// Helper function for path-prepend and path-append.  Takes an
+    /// environment variable name and a path entry as its arguments,
+    /// removes the entry from the variable's value if it is already
+    /// present, and then adds it to the start or the end of the
+    /// value.
+    fn path_add(&mut self, name: &str, prepend: bool) -> i32 {
+        if self.stack.len() < 2 {
+            let err_str = format!("{} requires two arguments", name);
+            self.print_error(&err_str);
+            return 0;
+        }
+
+        let entry_rr = self.stack.pop().unwrap();
+        let key_rr = self.stack.pop().unwrap();
+
+        let key_opt: Option<&str>;
+        to_str!(key_rr, key_opt);
+        let entry_opt: Option<&str>;
+        to_str!(entry_rr, entry_opt);
+
+        match (key_opt, entry_opt) {
+            (Some(key_s), Some(entry_s)) => {
+                if key_s.is_empty() {
+                    let err_str = format!("first {} argument must be a variable name", name);
+                    self.print_error(&err_str);
+                    return 0;
+                }
+                let entry = PathBuf::from(entry_s);
+                let mut components = match env::var_os(key_s) {
+                    Some(value) => env::split_paths(&value)
+                        .filter(|p| !p.as_os_str().is_empty() && *p != entry)
+                        .map(|p| p.to_string_lossy().to_string())
+                        .collect::<Vec<String>>(),
+                    None => Vec::new(),
+                };
+                if prepend {
+                    components.insert(0, entry_s.to_string());
+                } else {
+                    components.push(entry_s.to_string());
+                }
+                match self.join_path_components(name, &components) {
+                    Some(s) => {
+                        env::set_var(key_s, s);
+                        1
+                    }
+                    _ => 0,
+                }
+            }
+            (Some(_), _) => {
+                let err_str = format!("second {} argument must be a path", name);
+                self.print_error(&err_str);
+                0
+            }
+            (_, _) => {
+                let err_str = format!("first {} argument must be a variable name", name);
+                self.print_error(&err_str);
+                0
+            }
+        }
+    }
+
+    /// Takes an environment variable name and a path entry as its
+    /// arguments.  Adds the entry to the start of the variable's
+    /// value, removing any existing instances of the entry.
+    pub fn core_path_prepend(&mut self) -> i32 {
+        self.path_add("path-prepend", true)
+    }
+
+    /// Takes an environment variable name and a path entry as its
+    /// arguments.  Adds the entry to the end of the variable's value,
+    /// removing any existing instances of the entry.
+    pub fn core_path_append(&mut self) -> i32 {
+        self.path_add("path-append", false)
+    }
+
+    /// Takes a line from a .env file, and returns the variable name
+    /// and value from that line, or None if the line is not valid.
+    fn parse_env_line(line: &str) -> Option<(String, String)> {
+        let line = line.trim();
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+        let (key, value) = line.split_once('=')?;
+        let key = key.trim_end();
+        let mut key_chars = key.chars();
+        let first = key_chars.next()?;
+        if !(first.is_ascii_alphabetic() || first == '_')
+                || !key_chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return None;
+        }
+
+        let value = value.trim_start();
+        let mut chars = value.chars();
+        let parsed = match chars.next() {
+            Some('"') => {
+                let mut parsed = String::new();
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            'n' => parsed.push('\n'),
+                            't' => parsed.push('\t'),
+                            'r' => parsed.push('\r'),
+                            c => parsed.push(c),
+                        },
+                        c => parsed.push(c),
+                    }
+                }
+                let rest = chars.as_str().trim_start();
+                if !rest.is_empty() && !rest.starts_with('#') {
+                    return None;
+                }
+                parsed
+            }
+            Some('\'') => {
+                let rest = chars.as_str();
+                let end = rest.find('\'')?;
+                let remainder = rest[end + 1..].trim_start();
+                if !remainder.is_empty() && !remainder.starts_with('#') {
+                    return None;
+                }
+                rest[..end].to_string()
+            }
+            _ => {
+                let end = value.find(" #").unwrap_or(value.len());
+                value[..end].trim_end().to_string()
+            }
+        };
+        Some((key.to_string(), parsed))
+    }
+
+    /// Takes a .env file path and a boolean as its arguments.  Parses
+    /// the file's KEY=value lines.  If the boolean is true, then the
+    /// environment variables are set accordingly.  Otherwise, a hash
+    /// containing the variables is returned, and the environment is
+    /// not changed.
+    pub fn core_load_env(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("load-env requires two arguments");
+            return 0;
+        }
+
+        let set_rr = self.stack.pop().unwrap();
+        let path_rr = self.stack.pop().unwrap();
+        let path_opt: Option<&str>;
+        to_str!(path_rr, path_opt);
+        let path = match path_opt {
+            Some(s) => VM::expand_tilde(s),
+            _ => {
+                self.print_error("first load-env argument must be a string");
+                return 0;
+            }
+        };
+
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => {
+                let err_str = format!("unable to open file: {}", e);
+                self.print_error(&err_str);
+                return 0;
+            }
+        };
+
+        let mut hsh = IndexMap::new();
+        for (i, line) in contents.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            match VM::parse_env_line(line) {
+                Some((key, value)) => {
+                    hsh.insert(key, value);
+                }
+                _ => {
+                    let err_str = format!("load-env file has invalid syntax at line {}", i + 1);
+                    self.print_error(&err_str);
+                    return 0;
+                }
+            }
+        }
+
+        if set_rr.to_bool() {
+            for (key, value) in hsh {
+                env::set_var(key, value);
+            }
+        } else {
+            let hsh = hsh
+                .into_iter()
+                .map(|(key, value)| (key, new_string_value(value)))
+                .collect();
+            self.stack.push(Value::Hash(Rc::new(RefCell::new(hsh))));
+        }
+        1
+    }
 }
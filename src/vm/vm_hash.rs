@@ -374,6 +374,66 @@ impl VM {
         1
     }
 
+    /// Takes a container and a target value as its arguments.  Puts
+    /// a boolean onto the stack indicating whether the container
+    /// contains the target.  For a list, the target must be equal to
+    /// one of the list's elements; for a set, the target must be one
+    /// of the set's elements; for a hash, the target must be equal to
+    /// one of the hash's values; and for a string, the target must be
+    /// a substring.
+    pub fn core_contains(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("contains? requires two arguments");
+            return 0;
+        }
+
+        let target_rr = self.stack.pop().unwrap();
+        let container_rr = self.stack.pop().unwrap();
+
+        let res = match container_rr {
+            Value::List(lst) => lst
+                .borrow()
+                .iter()
+                .any(|e| self.opcode_eq_inner(e, &target_rr) == 1),
+            Value::Hash(map) => map
+                .borrow()
+                .values()
+                .any(|e| self.opcode_eq_inner(e, &target_rr) == 1),
+            Value::Set(map) => {
+                let target_opt: Option<&str>;
+                to_str!(target_rr, target_opt);
+                match target_opt {
+                    Some(s) => map.borrow().contains_key(s),
+                    _ => {
+                        self.print_error("second contains? argument must be string");
+                        return 0;
+                    }
+                }
+            }
+            _ => {
+                let container_opt: Option<&str>;
+                to_str!(container_rr, container_opt);
+                let target_opt: Option<&str>;
+                to_str!(target_rr, target_opt);
+                match (container_opt, target_opt) {
+                    (Some(c), Some(t)) => c.contains(t),
+                    (Some(_), _) => {
+                        self.print_error("second contains? argument must be string");
+                        return 0;
+                    }
+                    (_, _) => {
+                        self.print_error(
+                            "first contains? argument must be list/hash/set/string"
+                        );
+                        return 0;
+                    }
+                }
+            }
+        };
+        self.stack.push(Value::Bool(res));
+        1
+    }
+
     /// Takes a hash value, a key string, and a value as its
     /// arguments.  Puts the value into the hash against the specified
     /// key, and puts the updated hash back onto the stack.
@@ -511,4 +571,537 @@ impl VM {
         }
         1
     }
+
+    /// Takes a hash mapping from key to count (or a shiftable object,
+    /// in which case the counts are calculated by stringifying each
+    /// element) and a number as its arguments.  Puts a list of the
+    /// most frequent keys onto the stack, where each element is a
+    /// list containing the key and its count.  Keys with the same
+    /// count are returned in their original order.
+    pub fn core_top(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("top requires two arguments");
+            return 0;
+        }
+
+        let n_rr = self.stack.pop().unwrap();
+        let n = match n_rr.to_int() {
+            Some(n) if n >= 0 => n as usize,
+            _ => {
+                self.print_error("second top argument must be non-negative integer");
+                return 0;
+            }
+        };
+
+        let mut counts: IndexMap<String, i32> = IndexMap::new();
+        let object_rr = self.stack.pop().unwrap();
+        match object_rr {
+            Value::Hash(map) => {
+                for (k, v) in map.borrow().iter() {
+                    match v.to_int() {
+                        Some(count) => {
+                            counts.insert(k.to_string(), count);
+                        }
+                        _ => {
+                            self.print_error("top hash values must be integers");
+                            return 0;
+                        }
+                    }
+                }
+            }
+            _ => {
+                if !object_rr.is_shiftable() {
+                    self.print_error("first top argument must be hash or shiftable");
+                    return 0;
+                }
+                loop {
+                    self.stack.push(object_rr.clone());
+                    let shift_res = self.opcode_shift();
+                    if shift_res == 0 {
+                        return 0;
+                    }
+                    let value_rr = self.stack.pop().unwrap();
+                    if let Value::Null = value_rr {
+                        break;
+                    }
+                    let value_opt: Option<&str>;
+                    to_str!(value_rr, value_opt);
+                    match value_opt {
+                        Some(s) => {
+                            *counts.entry(s.to_string()).or_insert(0) += 1;
+                        }
+                        _ => {
+                            self.print_error("top element must be stringifiable");
+                            return 0;
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut entries: Vec<(String, i32)> = counts.into_iter().collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.1));
+        let lst = entries
+            .into_iter()
+            .take(n)
+            .map(|(k, count)| {
+                let mut pair = VecDeque::new();
+                pair.push_back(new_string_value(k));
+                pair.push_back(Value::Int(count));
+                Value::List(Rc::new(RefCell::new(pair)))
+            })
+            .collect::<VecDeque<Value>>();
+        self.stack.push(Value::List(Rc::new(RefCell::new(lst))));
+        1
+    }
+
+    /// Helper function for indexing a list of records.  Takes a list
+    /// of hashes and a key field name, and returns a map from each
+    /// distinct value of that field to the records having that value.
+    /// Raises an error if a record is not a hash.  If `missing` is
+    /// provided, then records that do not contain the key field are
+    /// added to it, and otherwise an error is raised for such
+    /// records.
+    pub fn index_records(
+        &mut self,
+        name: &str,
+        records_rr: &Value,
+        key: &str,
+        mut missing: Option<&mut Vec<Value>>,
+    ) -> Option<IndexMap<String, Vec<Value>>> {
+        let records = match records_rr {
+            Value::List(lst) => lst.borrow().iter().cloned().collect::<Vec<Value>>(),
+            _ => {
+                let err_str = format!("{} records must be in a list", name);
+                self.print_error(&err_str);
+                return None;
+            }
+        };
+
+        let mut index: IndexMap<String, Vec<Value>> = IndexMap::new();
+        for record_rr in records {
+            let field_rr = match record_rr {
+                Value::Hash(ref map) => map.borrow().get(key).cloned(),
+                _ => {
+                    let err_str = format!("{} record must be hash", name);
+                    self.print_error(&err_str);
+                    return None;
+                }
+            };
+            let field_rr = match (field_rr, missing.as_mut()) {
+                (Some(v), _) => v,
+                (None, Some(missing)) => {
+                    missing.push(record_rr);
+                    continue;
+                }
+                _ => {
+                    let err_str = format!("{} record does not contain key '{}'", name, key);
+                    self.print_error(&err_str);
+                    return None;
+                }
+            };
+            let field_opt: Option<&str>;
+            to_str!(field_rr, field_opt);
+            match field_opt {
+                Some(s) => {
+                    index.entry(s.to_string()).or_default().push(record_rr);
+                }
+                _ => {
+                    let err_str = format!("{} key value must be stringifiable", name);
+                    self.print_error(&err_str);
+                    return None;
+                }
+            }
+        }
+        Some(index)
+    }
+
+    /// Takes a list of hashes, a key field name, and a boolean
+    /// indicating whether records without the key field should be
+    /// collected separately, as its arguments.  Puts a hash onto the
+    /// stack that maps from each value of the key field to the record
+    /// with that value.  If multiple records have the same value,
+    /// then the value maps to a list of those records.  If the
+    /// boolean is true, then a list containing that hash and a list
+    /// of the records without the key field is put onto the stack
+    /// instead.  Otherwise, a record without the key field causes an
+    /// error.
+    pub fn core_pivot(&mut self) -> i32 {
+        if self.stack.len() < 3 {
+            self.print_error("pivot requires three arguments");
+            return 0;
+        }
+
+        let collect_missing_rr = self.stack.pop().unwrap();
+        let collect_missing = collect_missing_rr.to_bool();
+
+        let key_rr = self.stack.pop().unwrap();
+        let key_opt: Option<&str>;
+        to_str!(key_rr, key_opt);
+        let key = match key_opt {
+            Some(s) => s.to_string(),
+            _ => {
+                self.print_error("second pivot argument must be string");
+                return 0;
+            }
+        };
+
+        let mut records_rr = self.stack.pop().unwrap();
+        if records_rr.is_generator() {
+            self.stack.push(records_rr);
+            let res = self.generator_to_list();
+            if res == 0 {
+                return 0;
+            }
+            records_rr = self.stack.pop().unwrap();
+        }
+        let mut missing = Vec::new();
+        let missing_opt = if collect_missing { Some(&mut missing) } else { None };
+        let index_opt = self.index_records("pivot", &records_rr, &key, missing_opt);
+        match index_opt {
+            Some(index) => {
+                let mut map = IndexMap::new();
+                for (k, mut records) in index.into_iter() {
+                    let value = if records.len() == 1 {
+                        records.pop().unwrap()
+                    } else {
+                        Value::List(Rc::new(RefCell::new(records.into())))
+                    };
+                    map.insert(k, value);
+                }
+                let map_rr = Value::Hash(Rc::new(RefCell::new(map)));
+                if collect_missing {
+                    let missing_rr = Value::List(Rc::new(RefCell::new(missing.into())));
+                    let result: VecDeque<Value> = vec![map_rr, missing_rr].into();
+                    self.stack.push(Value::List(Rc::new(RefCell::new(result))));
+                } else {
+                    self.stack.push(map_rr);
+                }
+                1
+            }
+            _ => 0,
+        }
+    }
+
+    /// Helper function for the join-on forms.  Takes two lists of
+    /// hashes and a key field name as its arguments, and puts a list
+    /// of the merged records for each pair of records having the same
+    /// key value onto the stack.  If `left` is true, then records
+    /// from the first list that have no match are included, with the
+    /// fields from the second list set to null.  If `right` is true,
+    /// then the same applies for unmatched records from the second
+    /// list.
+    fn join_on_inner(&mut self, name: &str, left: bool, right: bool) -> i32 {
+        if self.stack.len() < 3 {
+            let err_str = format!("{} requires three arguments", name);
+            self.print_error(&err_str);
+            return 0;
+        }
+
+        let key_rr = self.stack.pop().unwrap();
+        let key_opt: Option<&str>;
+        to_str!(key_rr, key_opt);
+        let key = match key_opt {
+            Some(s) => s.to_string(),
+            _ => {
+                let err_str = format!("third {} argument must be string", name);
+                self.print_error(&err_str);
+                return 0;
+            }
+        };
+
+        let mut lists = Vec::new();
+        for _ in 0..2 {
+            let mut records_rr = self.stack.pop().unwrap();
+            if records_rr.is_generator() {
+                self.stack.push(records_rr);
+                let res = self.generator_to_list();
+                if res == 0 {
+                    return 0;
+                }
+                records_rr = self.stack.pop().unwrap();
+            }
+            lists.push(records_rr);
+        }
+        let right_rr = lists.remove(0);
+        let left_rr = lists.remove(0);
+
+        let left_index = match self.index_records(name, &left_rr, &key, None) {
+            Some(index) => index,
+            _ => return 0,
+        };
+        let right_index = match self.index_records(name, &right_rr, &key, None) {
+            Some(index) => index,
+            _ => return 0,
+        };
+
+        /* Collect the field names from each side, for null-filling
+         * unmatched records. */
+        let field_names = |index: &IndexMap<String, Vec<Value>>| {
+            let mut names: IndexMap<String, ()> = IndexMap::new();
+            for records in index.values() {
+                for record_rr in records {
+                    if let Value::Hash(map) = record_rr {
+                        for k in map.borrow().keys() {
+                            names.insert(k.to_string(), ());
+                        }
+                    }
+                }
+            }
+            names.into_keys().collect::<Vec<String>>()
+        };
+        let left_fields = field_names(&left_index);
+        let right_fields = field_names(&right_index);
+
+        let merge = |record1: Option<&Value>, record2: Option<&Value>| {
+            let mut map = IndexMap::new();
+            for (record_opt, fields) in [(record1, &left_fields), (record2, &right_fields)] {
+                match record_opt {
+                    Some(Value::Hash(hsh)) => {
+                        for (k, v) in hsh.borrow().iter() {
+                            map.insert(k.to_string(), v.clone());
+                        }
+                    }
+                    _ => {
+                        for k in fields.iter() {
+                            if !map.contains_key(k) {
+                                map.insert(k.to_string(), Value::Null);
+                            }
+                        }
+                    }
+                }
+            }
+            Value::Hash(Rc::new(RefCell::new(map)))
+        };
+
+        let mut results = VecDeque::new();
+        if let Value::List(lst) = left_rr {
+            for left_record_rr in lst.borrow().iter() {
+                let key_value = match left_record_rr {
+                    Value::Hash(map) => map.borrow().get(&key).unwrap().clone(),
+                    _ => unreachable!(),
+                };
+                let key_value_opt: Option<&str>;
+                to_str!(key_value, key_value_opt);
+                match right_index.get(key_value_opt.unwrap()) {
+                    Some(right_records) => {
+                        for right_record_rr in right_records {
+                            results.push_back(merge(Some(left_record_rr), Some(right_record_rr)));
+                        }
+                    }
+                    _ => {
+                        if left {
+                            results.push_back(merge(Some(left_record_rr), None));
+                        }
+                    }
+                }
+            }
+        }
+        if right {
+            for (k, right_records) in right_index.iter() {
+                if !left_index.contains_key(k) {
+                    for right_record_rr in right_records {
+                        results.push_back(merge(None, Some(right_record_rr)));
+                    }
+                }
+            }
+        }
+        self.stack.push(Value::List(Rc::new(RefCell::new(results))));
+        1
+    }
+
+    /// Takes two lists of hashes and a key field name as its
+    /// arguments.  Puts a list of the merged records for each pair of
+    /// records having the same key value onto the stack.
+    pub fn core_join_on(&mut self) -> i32 {
+        self.join_on_inner("join-on", false, false)
+    }
+
+    /// As per join-on, except that records from the first list that
+    /// have no match are also included.
+    pub fn core_left_join_on(&mut self) -> i32 {
+        self.join_on_inner("left-join-on", true, false)
+    }
+
+    /// As per join-on, except that records from either list that
+    /// have no match are also included.
+    pub fn core_outer_join_on(&mut self) -> i32 {
+        self.join_on_inner("outer-join-on", true, true)
+    }
+
+    /// Helper function for flattening a hash.  Adds each leaf value
+    /// from the value to the map, keyed by its path.
+    fn flatten_hash_inner(
+        value_rr: &Value,
+        prefix: Option<String>,
+        separator: &str,
+        map: &mut IndexMap<String, Value>,
+    ) {
+        let path = |k: &str| match prefix {
+            Some(ref p) => format!("{}{}{}", p, separator, k),
+            None => k.to_string(),
+        };
+        match value_rr {
+            Value::Hash(hsh) if !hsh.borrow().is_empty() => {
+                for (k, v) in hsh.borrow().iter() {
+                    VM::flatten_hash_inner(v, Some(path(k)), separator, map);
+                }
+            }
+            Value::List(lst) if !lst.borrow().is_empty() => {
+                for (i, v) in lst.borrow().iter().enumerate() {
+                    VM::flatten_hash_inner(v, Some(path(&i.to_string())), separator, map);
+                }
+            }
+            _ => {
+                map.insert(prefix.unwrap_or_default(), value_rr.clone());
+            }
+        }
+    }
+
+    /// Takes a hash and a separator string as its arguments.  Puts a
+    /// flat hash onto the stack, where each key is the path to a
+    /// value in the original hash, with the path components joined
+    /// by the separator.  List indexes are included in the path.
+    pub fn core_flatten_hash(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("flatten-hash requires two arguments");
+            return 0;
+        }
+
+        let separator_rr = self.stack.pop().unwrap();
+        let separator_opt: Option<&str>;
+        to_str!(separator_rr, separator_opt);
+        let separator = match separator_opt {
+            Some(s) => s.to_string(),
+            _ => {
+                self.print_error("second flatten-hash argument must be string");
+                return 0;
+            }
+        };
+
+        let hash_rr = self.stack.pop().unwrap();
+        if !matches!(hash_rr, Value::Hash(_)) {
+            self.print_error("first flatten-hash argument must be hash");
+            return 0;
+        }
+
+        let mut map = IndexMap::new();
+        if let Value::Hash(ref hsh) = hash_rr {
+            for (k, v) in hsh.borrow().iter() {
+                VM::flatten_hash_inner(v, Some(k.to_string()), &separator, &mut map);
+            }
+        }
+        self.stack.push(Value::Hash(Rc::new(RefCell::new(map))));
+        1
+    }
+
+    /// Helper function for unflattening a hash.  Converts any hash
+    /// created during unflattening whose keys are the indexes 0 to
+    /// n-1 (in order) into a list.  Hashes that were values in the
+    /// original hash are left as-is.
+    fn unflatten_lists(value_rr: Value, created: &HashSet<usize>) -> Value {
+        match value_rr {
+            Value::Hash(hsh) if created.contains(&(Rc::as_ptr(&hsh) as usize)) => {
+                let map = hsh.replace(IndexMap::new());
+                let is_list = map
+                    .keys()
+                    .enumerate()
+                    .all(|(i, k)| *k == i.to_string());
+                if is_list {
+                    let lst = map
+                        .into_values()
+                        .map(|v| VM::unflatten_lists(v, created))
+                        .collect::<VecDeque<Value>>();
+                    Value::List(Rc::new(RefCell::new(lst)))
+                } else {
+                    let new_map = map
+                        .into_iter()
+                        .map(|(k, v)| (k, VM::unflatten_lists(v, created)))
+                        .collect::<IndexMap<String, Value>>();
+                    Value::Hash(Rc::new(RefCell::new(new_map)))
+                }
+            }
+            _ => value_rr,
+        }
+    }
+
+    /// Takes a flat hash and a separator string as its arguments.
+    /// Puts a nested hash onto the stack, where each key from the
+    /// flat hash is split on the separator to determine the path to
+    /// the value in the new hash.  Nested hashes with the keys 0 to
+    /// n-1 are converted into lists.
+    pub fn core_unflatten_hash(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("unflatten-hash requires two arguments");
+            return 0;
+        }
+
+        let separator_rr = self.stack.pop().unwrap();
+        let separator_opt: Option<&str>;
+        to_str!(separator_rr, separator_opt);
+        let separator = match separator_opt {
+            Some(s) if !s.is_empty() => s.to_string(),
+            _ => {
+                self.print_error("second unflatten-hash argument must be non-empty string");
+                return 0;
+            }
+        };
+
+        let hash_rr = self.stack.pop().unwrap();
+        let entries = match hash_rr {
+            Value::Hash(hsh) => hsh
+                .borrow()
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.clone()))
+                .collect::<Vec<(String, Value)>>(),
+            _ => {
+                self.print_error("first unflatten-hash argument must be hash");
+                return 0;
+            }
+        };
+
+        let root = Rc::new(RefCell::new(IndexMap::new()));
+        let mut created = HashSet::new();
+        for (key, value_rr) in entries {
+            let parts = key.split(&separator).collect::<Vec<&str>>();
+            let mut current = root.clone();
+            for part in parts[..parts.len() - 1].iter() {
+                let next_opt = current.borrow().get(*part).cloned();
+                let next = match next_opt {
+                    Some(Value::Hash(next))
+                        if created.contains(&(Rc::as_ptr(&next) as usize)) => next,
+                    None => {
+                        let next = Rc::new(RefCell::new(IndexMap::new()));
+                        created.insert(Rc::as_ptr(&next) as usize);
+                        current
+                            .borrow_mut()
+                            .insert(part.to_string(), Value::Hash(next.clone()));
+                        next
+                    }
+                    _ => {
+                        let err_str = format!("unflatten-hash key '{}' conflicts with another key", key);
+                        self.print_error(&err_str);
+                        return 0;
+                    }
+                };
+                current = next;
+            }
+            let last = parts[parts.len() - 1];
+            if current.borrow().contains_key(last) {
+                let err_str = format!("unflatten-hash key '{}' conflicts with another key", key);
+                self.print_error(&err_str);
+                return 0;
+            }
+            current.borrow_mut().insert(last.to_string(), value_rr);
+        }
+
+        /* The top level is always a hash, even if its keys are
+         * indexes. */
+        let map = root.replace(IndexMap::new())
+            .into_iter()
+            .map(|(k, v)| (k, VM::unflatten_lists(v, &created)))
+            .collect::<IndexMap<String, Value>>();
+        self.stack.push(Value::Hash(Rc::new(RefCell::new(map))));
+        1
+    }
 }
@@ -2,19 +2,23 @@ use std::cell::RefCell;
 use std::fs::metadata;
 use std::fs::symlink_metadata;
 use std::fs::File;
+use std::io;
 use std::io::BufReader;
 use std::io::BufWriter;
 use std::io::Write;
+use std::os::fd::{AsFd, OwnedFd};
 use std::rc::Rc;
 use std::thread;
 use std::time;
 
 use lazy_static::lazy_static;
+use nix::libc::mode_t;
+use nix::sys::stat::{umask, Mode};
 use nix::unistd::AccessFlags;
 use regex::Regex;
 use tempfile::{NamedTempFile, TempDir};
 
-use crate::chunk::{Value, BufReaderWithBuffer};
+use crate::chunk::{Value, BufReaderWithBuffer, ReverseFileReader};
 use crate::vm::*;
 
 lazy_static! {
@@ -138,6 +142,19 @@ impl VM {
                     }
                 }
             }
+            Value::ReverseFileReader(ref mut rfr) => {
+                let str_res = rfr.borrow_mut().readline();
+
+                match str_res {
+                    Some(v) => {
+                        self.stack.push(v);
+                    }
+                    _ => {
+                        self.print_error("unable to read from file");
+                        return 0;
+                    }
+                }
+            }
             Value::TcpSocketReader(ref mut brwb) => {
                 loop {
                     let str_res = brwb.borrow_mut().readline();
@@ -253,6 +270,9 @@ impl VM {
                 }
                 match file_writer {
                     Value::FileWriter(ref mut line_writer) => {
+                        if !self.flush_print_output() {
+                            return 0;
+                        }
                         let res =
                             line_writer.borrow_mut().write_all(&bytes);
                         match res {
@@ -312,6 +332,9 @@ impl VM {
                     let mut file_writer = self.stack.pop().unwrap();
                     match file_writer {
                         Value::FileWriter(ref mut line_writer) => {
+                            if !self.flush_print_output() {
+                                return 0;
+                            }
                             let res = line_writer.borrow_mut().write_all(s.as_bytes());
                             match res {
                                 Ok(_) => {
@@ -364,7 +387,7 @@ impl VM {
         let mut file_rr = self.stack.pop().unwrap();
 
         match file_rr {
-            Value::FileReader(_) => {
+            Value::FileReader(_) | Value::ReverseFileReader(_) => {
                 // No action required.
                 1
             }
@@ -386,6 +409,191 @@ impl VM {
         }
     }
 
+    /// Puts a FileReader object for standard input onto the stack.
+    /// The same object is returned by each call, so that input
+    /// buffered by one call is not lost to later calls.
+    pub fn core_stdin(&mut self) -> i32 {
+        if let Some(stdin_rr) = &self.stdin {
+            self.stack.push(stdin_rr.clone());
+            return 1;
+        }
+        match io::stdin().as_fd().try_clone_to_owned() {
+            Ok(fd) => {
+                let stdin_rr = Value::FileReader(Rc::new(RefCell::new(
+                    BufReaderWithBuffer::new(BufReader::new(File::from(fd)))
+                )));
+                self.stdin = Some(stdin_rr.clone());
+                self.stack.push(stdin_rr);
+                1
+            }
+            Err(e) => {
+                let err_str = format!("unable to open standard input: {}", e);
+                self.print_error(&err_str);
+                0
+            }
+        }
+    }
+
+    /// Flushes any output from print and println that is still
+    /// buffered for standard output, so that it is not written after
+    /// output sent to a file writer (e.g. the writer returned by
+    /// stdout).  Returns a boolean indicating whether the flush was
+    /// successful.
+    fn flush_print_output(&mut self) -> bool {
+        let res = io::stdout().flush();
+        if let Err(e) = res {
+            let err_str = format!("unable to flush data: {}", e);
+            self.print_error(&err_str);
+            return false;
+        }
+        true
+    }
+
+    /// Puts a FileWriter object for the given standard stream onto
+    /// the stack.  The writer is unbuffered, and output from print
+    /// and println is flushed before each write to a file writer, so
+    /// that the two are not written out of order.
+    fn std_writer(&mut self, name: &str, fd: io::Result<OwnedFd>) -> i32 {
+        match fd {
+            Ok(fd) => {
+                self.stack.push(Value::FileWriter(Rc::new(RefCell::new(
+                    BufWriter::with_capacity(0, File::from(fd))
+                ))));
+                1
+            }
+            Err(e) => {
+                let err_str = format!("unable to open {}: {}", name, e);
+                self.print_error(&err_str);
+                0
+            }
+        }
+    }
+
+    /// Puts a FileWriter object for standard output onto the stack.
+    pub fn core_stdout(&mut self) -> i32 {
+        let fd = io::stdout().as_fd().try_clone_to_owned();
+        self.std_writer("standard output", fd)
+    }
+
+    /// Puts a FileWriter object for standard error onto the stack.
+    pub fn core_stderr(&mut self) -> i32 {
+        let fd = io::stderr().as_fd().try_clone_to_owned();
+        self.std_writer("standard error", fd)
+    }
+
+    /// Takes a file path as its single argument.  Puts a generator
+    /// onto the stack that returns the lines of the file in reverse
+    /// order, starting with the last line.
+    pub fn core_tac(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("tac requires one argument");
+            return 0;
+        }
+
+        let path_rr = self.stack.pop().unwrap();
+        let path_str_opt: Option<&str>;
+        to_str!(path_rr, path_str_opt);
+
+        match path_str_opt {
+            Some(s) => {
+                let ss = VM::expand_tilde(s);
+                let is_dir = metadata(ss.clone()).map(|m| m.is_dir()).unwrap_or(false);
+                if is_dir {
+                    self.print_error("unable to open file: is a directory");
+                    return 0;
+                }
+                let rfr_res = File::open(ss).and_then(ReverseFileReader::new);
+                match rfr_res {
+                    Ok(rfr) => {
+                        self.stack.push(Value::ReverseFileReader(Rc::new(RefCell::new(rfr))));
+                        1
+                    }
+                    Err(e) => {
+                        let err_str = format!("unable to open file: {}", e);
+                        self.print_error(&err_str);
+                        0
+                    }
+                }
+            }
+            _ => {
+                self.print_error("tac argument must be a string");
+                0
+            }
+        }
+    }
+
+    /// Takes a file path, a mode string, and a callable as its
+    /// arguments.  Opens the file (as per open), calls the callable
+    /// with the resulting FileReader or FileWriter object, and then
+    /// closes the object, regardless of whether the call succeeded.
+    pub fn core_with_file(&mut self) -> i32 {
+        if self.stack.len() < 3 {
+            self.print_error("with-file requires three arguments");
+            return 0;
+        }
+
+        let fn_rr = self.stack.pop().unwrap();
+        if !fn_rr.is_callable() {
+            self.print_error("third with-file argument must be callable");
+            return 0;
+        }
+
+        let open_res = self.opcode_open();
+        if open_res == 0 {
+            return 0;
+        }
+        let file_rr = self.stack.last().unwrap().clone();
+        let call_res = self.call(OpCode::Call, fn_rr);
+        self.stack.push(file_rr);
+        let close_res = self.core_close();
+        if !call_res {
+            return 0;
+        }
+        close_res
+    }
+
+    /// Takes a callable as its single argument.  Creates a new
+    /// temporary directory, calls the callable with the path to
+    /// that directory, and then removes the directory and its
+    /// contents.  The directory is removed even if the callable
+    /// raises an error.
+    pub fn core_with_tempdir(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("with-tempdir requires one argument");
+            return 0;
+        }
+
+        let fn_rr = self.stack.pop().unwrap();
+        if !fn_rr.is_callable() {
+            self.print_error("with-tempdir argument must be callable");
+            return 0;
+        }
+
+        let td = match TempDir::new() {
+            Ok(td) => td,
+            Err(e) => {
+                let err_str = format!("unable to open temporary directory: {}", e);
+                self.print_error(&err_str);
+                return 0;
+            }
+        };
+        self.stack
+            .push(new_string_value(td.path().to_str().unwrap().to_string()));
+        let call_res = self.call(OpCode::Call, fn_rr);
+        let close_res = td.close();
+        if !call_res {
+            return 0;
+        }
+        match close_res {
+            Ok(_) => 1,
+            Err(e) => {
+                let err_str = format!("unable to remove temporary directory: {}", e);
+                self.print_error(&err_str);
+                0
+            }
+        }
+    }
+
     /// Takes a directory path as its single argument.  Opens the
     /// directory and places a DirectoryHandle object for the
     /// directory onto the stack.
@@ -633,6 +841,37 @@ impl VM {
         1
     }
 
+    /// Returns the current umask as an integer.
+    pub fn core_umask(&mut self) -> i32 {
+        let mask = umask(Mode::empty());
+        umask(mask);
+        self.stack.push(Value::Int(mask.bits() as i32));
+        1
+    }
+
+    /// Takes a mode integer as its single argument, sets the umask
+    /// to that mode, and returns the previous umask.
+    pub fn core_set_umask(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("set-umask requires one argument");
+            return 0;
+        }
+
+        let mask_rr = self.stack.pop().unwrap();
+        match mask_rr.to_int() {
+            Some(n) if (0..=0o777).contains(&n) => {
+                let mode = Mode::from_bits_truncate(n as mode_t);
+                let previous = umask(mode);
+                self.stack.push(Value::Int(previous.bits() as i32));
+                1
+            }
+            _ => {
+                self.print_error("set-umask argument must be integer between 0 and 511");
+                0
+            }
+        }
+    }
+
     /// Puts a path and a FileReader on the stack for a new temporary
     /// file.
     pub fn opcode_tempfile(&mut self) -> i32 {
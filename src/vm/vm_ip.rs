@@ -53,6 +53,34 @@ fn int_to_ipv6_addr(n: BigUint) -> Ipv6Addr {
     Ipv6Addr::new(o1, o2, o3, o4, o5, o6, o7, o8)
 }
 
+/// Returns the first and last addresses of the IP object as
+/// integers, or None if the value is not an IP object.
+fn ip_int_bounds(value: &Value) -> Option<(BigInt, BigInt)> {
+    match value {
+        Value::Ipv4(ipv4net) => {
+            let first = ipv4_addr_to_int(ipv4net.network()) as u64;
+            let last = first | ((1u64 << (32 - ipv4net.prefix_len())) - 1);
+            Some((BigInt::from(first), BigInt::from(last)))
+        }
+        Value::Ipv4Range(ipv4range) => Some((
+            BigInt::from(ipv4_addr_to_int(ipv4range.s)),
+            BigInt::from(ipv4_addr_to_int(ipv4range.e)),
+        )),
+        Value::Ipv6(ipv6net) => {
+            let prefix_mask =
+                (BigUint::from(1u8) << (128 - ipv6net.prefix_len())) - BigUint::from(1u8);
+            let first = ipv6_addr_to_int(ipv6net.network());
+            let last = first.clone() | prefix_mask;
+            Some((BigInt::from(first), BigInt::from(last)))
+        }
+        Value::Ipv6Range(ipv6range) => Some((
+            BigInt::from(ipv6_addr_to_int(ipv6range.s)),
+            BigInt::from(ipv6_addr_to_int(ipv6range.e)),
+        )),
+        _ => None,
+    }
+}
+
 /// Convert an IPv4 range (arbitrary start-end pair) into a list of
 /// IPv4Net objects (prefixes).
 fn ipv4range_to_nets(ipv4range: Ipv4Range) -> VecDeque<Ipv4Net> {
@@ -484,29 +512,9 @@ impl VM {
         }
 
         let ip_rr = self.stack.pop().unwrap();
-        match ip_rr {
-            Value::Ipv4(ipv4net) => {
-                let ipv4addr_int = ipv4_addr_to_int(ipv4net.network());
-                let ipv4addr_val = Value::BigInt(BigInt::from(ipv4addr_int));
-                self.stack.push(ipv4addr_val);
-                1
-            }
-            Value::Ipv4Range(ipv4range) => {
-                let ipv4addr_int = ipv4_addr_to_int(ipv4range.s);
-                let ipv4addr_val = Value::BigInt(BigInt::from(ipv4addr_int));
-                self.stack.push(ipv4addr_val);
-                1
-            }
-            Value::Ipv6(ipv6net) => {
-                let ipv6addr_int = ipv6_addr_to_int(ipv6net.network());
-                let ipv6addr_val = Value::BigInt(BigInt::from(ipv6addr_int));
-                self.stack.push(ipv6addr_val);
-                1
-            }
-            Value::Ipv6Range(ipv6range) => {
-                let ipv6addr_int = ipv6_addr_to_int(ipv6range.s);
-                let ipv6addr_val = Value::BigInt(BigInt::from(ipv6addr_int));
-                self.stack.push(ipv6addr_val);
+        match ip_int_bounds(&ip_rr) {
+            Some((first, _)) => {
+                self.stack.push(Value::BigInt(first));
                 1
             }
             _ => {
@@ -580,43 +588,16 @@ impl VM {
         }
 
         let ip_rr = self.stack.pop().unwrap();
-        match ip_rr {
-            Value::Ipv4(ipv4net) => {
-                if ipv4_addr_to_int(ipv4net.network()) == 0 && ipv4net.prefix_len() == 0 {
-                    let lastaddr_val = Value::BigInt(BigInt::from_u32(0xFFFFFFFF).unwrap());
-                    self.stack.push(lastaddr_val);
-                    return 1;
-                }
-                let ipv4addr_int =
-                    ipv4_addr_to_int(ipv4net.network()) | ((1 << (32 - ipv4net.prefix_len())) - 1);
-                let lastaddr_val = Value::BigInt(BigInt::from_u32(ipv4addr_int).unwrap());
-                self.stack.push(lastaddr_val);
-                return 1;
-            }
-            Value::Ipv4Range(ipv4range) => {
-                let ipv4addr_int = ipv4_addr_to_int(ipv4range.e);
-                let lastaddr_val = Value::BigInt(BigInt::from(ipv4addr_int));
-                self.stack.push(lastaddr_val);
-            }
-            Value::Ipv6(ipv6net) => {
-                let prefix_mask =
-                    (BigUint::from(1u8) << (128 - ipv6net.prefix_len())) - BigUint::from(1u8);
-                let ipv6addr_int = ipv6_addr_to_int(ipv6net.network()) | prefix_mask;
-                let lastaddr_val = Value::BigInt(BigInt::from(ipv6addr_int));
-                self.stack.push(lastaddr_val);
-            }
-            Value::Ipv6Range(ipv6range) => {
-                let ipv6addr_int = ipv6_addr_to_int(ipv6range.e);
-                let lastaddr_val = Value::BigInt(BigInt::from(ipv6addr_int));
-                self.stack.push(lastaddr_val);
+        match ip_int_bounds(&ip_rr) {
+            Some((_, last)) => {
+                self.stack.push(Value::BigInt(last));
+                1
             }
             _ => {
                 self.print_error("ip.last-addr-int argument must be ip object");
-                return 0;
+                0
             }
         }
-
-        1
     }
 
     /// Returns the number of hosts covered by this IP object.
@@ -763,6 +744,123 @@ impl VM {
         1
     }
 
+    /// Returns the first and last addresses of the IP object as
+    /// integers.
+    pub fn core_ip_to_range_ints(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("ip.to-range-ints requires one argument");
+            return 0;
+        }
+
+        let ip_rr = self.stack.pop().unwrap();
+        match ip_int_bounds(&ip_rr) {
+            Some((first, last)) => {
+                self.stack.push(Value::BigInt(first));
+                self.stack.push(Value::BigInt(last));
+                1
+            }
+            _ => {
+                self.print_error("ip.to-range-ints argument must be ip object");
+                0
+            }
+        }
+    }
+
+    /// Takes an IP object or an address integer, along with start
+    /// and end address integers, and returns a boolean indicating
+    /// whether the IP object or address falls within that range.
+    pub fn core_ip_int_contains(&mut self) -> i32 {
+        if self.stack.len() < 3 {
+            self.print_error("ip.int-contains? requires three arguments");
+            return 0;
+        }
+
+        let end_rr = self.stack.pop().unwrap();
+        let start_rr = self.stack.pop().unwrap();
+        let ip_rr = self.stack.pop().unwrap();
+
+        let end = match end_rr.to_bigint() {
+            Some(n) => n,
+            _ => {
+                self.print_error("third ip.int-contains? argument must be integer");
+                return 0;
+            }
+        };
+        let start = match start_rr.to_bigint() {
+            Some(n) => n,
+            _ => {
+                self.print_error("second ip.int-contains? argument must be integer");
+                return 0;
+            }
+        };
+        let bounds = match ip_int_bounds(&ip_rr) {
+            Some(bounds) => Some(bounds),
+            _ => ip_rr.to_bigint().map(|n| (n.clone(), n)),
+        };
+        match bounds {
+            Some((first, last)) => {
+                self.stack.push(Value::Bool(first >= start && last <= end));
+                1
+            }
+            _ => {
+                self.print_error("first ip.int-contains? argument must be ip object or integer");
+                0
+            }
+        }
+    }
+
+    /// Sorts a list or generator of IP objects by address.  IPv4
+    /// objects are ordered before IPv6 objects, and where two objects
+    /// have the same first address, the larger object is ordered
+    /// first.
+    pub fn core_ip_sort(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("ip.sort requires one argument");
+            return 0;
+        }
+
+        let value_rr = self.stack.pop().unwrap();
+        if value_rr.is_generator() {
+            self.stack.push(value_rr);
+            let res = self.generator_to_list();
+            if res == 0 {
+                return 0;
+            }
+            return self.core_ip_sort();
+        }
+
+        match value_rr {
+            Value::List(ref lst) => {
+                let mut keyed = Vec::new();
+                for e in lst.borrow().iter() {
+                    let version = match e {
+                        Value::Ipv4(_) | Value::Ipv4Range(_) => 4,
+                        _ => 6,
+                    };
+                    match ip_int_bounds(e) {
+                        Some((first, last)) => {
+                            keyed.push(((version, first, std::cmp::Reverse(last)), e.clone()));
+                        }
+                        _ => {
+                            self.print_error("ip.sort argument must contain ip objects");
+                            return 0;
+                        }
+                    }
+                }
+                keyed.sort_by(|a, b| a.0.cmp(&b.0));
+                let sorted = keyed.into_iter().map(|(_, e)| e).collect();
+                *lst.borrow_mut() = sorted;
+            }
+            _ => {
+                self.print_error("ip.sort argument must be list or generator");
+                return 0;
+            }
+        }
+
+        self.stack.push(value_rr);
+        1
+    }
+
     /// Converts an arbitrary value into a list of IP net objects.
     pub fn value_to_nets(
         &mut self,
@@ -910,4 +1008,64 @@ impl VM {
             None => 0,
         }
     }
+
+    /// Takes a list or generator of IP objects or IP address/range
+    /// strings, along with a boolean.  If the boolean is false, then
+    /// returns an IP set object for the distinct addresses.  If it
+    /// is true, then returns a hash mapping each distinct address to
+    /// the number of times it appears.
+    pub fn core_dedup_ips(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("dedup-ips requires two arguments");
+            return 0;
+        }
+
+        let counts_rr = self.stack.pop().unwrap();
+        let mut lst_rr = self.stack.pop().unwrap();
+        if !counts_rr.to_bool() {
+            self.stack.push(lst_rr);
+            return self.core_ips();
+        }
+
+        if lst_rr.is_generator() {
+            self.stack.push(lst_rr);
+            let res = self.generator_to_list();
+            if res == 0 {
+                return 0;
+            }
+            lst_rr = self.stack.pop().unwrap();
+        }
+        let lst = match lst_rr {
+            Value::List(lst) => lst,
+            _ => {
+                self.print_error("first dedup-ips argument must be list or generator");
+                return 0;
+            }
+        };
+
+        let mut counts: IndexMap<String, Value> = IndexMap::new();
+        for el in lst.borrow().iter() {
+            let ip_rr = match el {
+                Value::Ipv4(_) | Value::Ipv6(_) | Value::Ipv4Range(_)
+                        | Value::Ipv6Range(_) => el.clone(),
+                _ => {
+                    self.stack.push(el.clone());
+                    let res = self.core_ip();
+                    if res == 0 {
+                        return 0;
+                    }
+                    self.stack.pop().unwrap()
+                }
+            };
+            let key = ip_rr.to_string().unwrap();
+            let count = match counts.get(&key) {
+                Some(Value::Int(n)) => n + 1,
+                _ => 1,
+            };
+            counts.insert(key, Value::Int(count));
+        }
+
+        self.stack.push(Value::Hash(Rc::new(RefCell::new(counts))));
+        1
+    }
 }
@@ -1,14 +1,23 @@
 use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::convert::TryFrom;
+use std::fs::File;
 use std::rc::Rc;
 
-use indexmap::IndexMap;
-use num_bigint::ToBigInt;
+use indexmap::{IndexMap, IndexSet};
+use lazy_static::lazy_static;
+use num_bigint::{BigInt, ToBigInt};
+use num_traits::ToPrimitive;
+use regex::Regex;
 
-use crate::chunk::Value;
+use crate::chunk::{BufReaderWithBuffer, Value};
 use crate::vm::*;
 
+lazy_static! {
+    static ref JSON_NUMBER: Regex =
+        Regex::new("^-?(0|[1-9]\\d*)(\\.\\d+)?([eE][+-]?\\d+)?$").unwrap();
+}
+
 /// Converts a serde_json object into a value.
 fn convert_from_json(v: &serde_json::value::Value) -> Value {
     match v {
@@ -93,6 +102,651 @@ fn convert_to_json(v: &Value) -> String {
     }
 }
 
+/// The key ordering and filtering options for to-json-with.
+struct JsonKeyOptions {
+    order: Vec<String>,
+    include: Option<Vec<String>>,
+    exclude: Vec<String>,
+    recursive: bool,
+}
+
+/// Converts a list or set of keys into a vector of strings.
+fn value_to_key_list(v: &Value) -> Option<Vec<String>> {
+    match v {
+        Value::List(lst) => {
+            let mut keys = Vec::new();
+            for e in lst.borrow().iter() {
+                let key_opt: Option<&str>;
+                to_str!(e, key_opt);
+                keys.push(key_opt?.to_string());
+            }
+            Some(keys)
+        }
+        Value::Set(map) => Some(map.borrow().keys().cloned().collect()),
+        _ => None,
+    }
+}
+
+/// Convert a value into a JSON string, applying the key options to
+/// hashes.  If the options are not recursive, then they are applied
+/// only to a top-level hash, or to the hashes in a top-level list.
+fn convert_to_json_with(v: &Value, opts: &JsonKeyOptions, depth: usize) -> String {
+    let apply = opts.recursive || depth == 0;
+    match v {
+        Value::List(lst) => {
+            let s = lst
+                .borrow()
+                .iter()
+                .map(|e| {
+                    if opts.recursive || (depth == 0 && matches!(e, Value::Hash(_))) {
+                        convert_to_json_with(e, opts, depth)
+                    } else {
+                        convert_to_json(e)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            format!("[{}]", s)
+        }
+        Value::Hash(vm) if apply => {
+            let map = vm.borrow();
+            let mut keys: IndexSet<&String> = IndexSet::new();
+            for k in opts.order.iter() {
+                if let Some((k, _)) = map.get_key_value(k) {
+                    keys.insert(k);
+                }
+            }
+            keys.extend(map.keys());
+            let s = keys
+                .into_iter()
+                .filter(|k| {
+                    opts.include.as_ref().is_none_or(|include| include.contains(k))
+                        && !opts.exclude.contains(k)
+                })
+                .map(|k| {
+                    format!("\"{}\":{}", k,
+                            convert_to_json_with(map.get(k).unwrap(), opts, depth + 1))
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            format!("{{{}}}", s)
+        }
+        _ => convert_to_json(v),
+    }
+}
+
+/// Returns whether the value matches the given schema type name.
+fn json_type_matches(v: &Value, type_name: &str) -> bool {
+    match type_name {
+        "any" => true,
+        "number" => matches!(v, Value::Int(_) | Value::BigInt(_) | Value::Float(_)),
+        "int" => matches!(v, Value::Int(_) | Value::BigInt(_)),
+        _ => v.type_string() == type_name,
+    }
+}
+
+/// Validates a value against a schema (see core_json_validate),
+/// adding a message to the error list for each validation failure.
+/// Returns an error message if the schema itself is invalid.
+fn validate_json(
+    v: &Value,
+    schema: &Value,
+    path: &str,
+    errors: &mut VecDeque<Value>,
+) -> Result<(), String> {
+    let schema = match schema {
+        Value::Hash(map) => map.borrow(),
+        _ => return Err(format!("schema for '{}' must be hash", path)),
+    };
+    for k in schema.keys() {
+        if !matches!(k.as_str(), "type" | "required" | "properties" | "items") {
+            return Err(format!("schema key '{}' is not supported", k));
+        }
+    }
+
+    if let Some(type_rr) = schema.get("type") {
+        let type_names = match value_to_key_list(type_rr) {
+            Some(type_names) => type_names,
+            _ => {
+                let type_opt: Option<&str>;
+                to_str!(type_rr, type_opt);
+                match type_opt {
+                    Some(type_name) => vec![type_name.to_string()],
+                    _ => return Err("schema type must be string or list".to_string()),
+                }
+            }
+        };
+        if !type_names.iter().any(|t| json_type_matches(v, t)) {
+            errors.push_back(new_string_value(format!(
+                "{}: expected {}, got {}",
+                path,
+                type_names.join(" or "),
+                v.type_string()
+            )));
+            return Ok(());
+        }
+    }
+
+    if let Value::Hash(map) = v {
+        let map = map.borrow();
+        if let Some(required_rr) = schema.get("required") {
+            let required = match value_to_key_list(required_rr) {
+                Some(required) => required,
+                _ => return Err("schema required must be list or set".to_string()),
+            };
+            for k in required.iter() {
+                if !map.contains_key(k) {
+                    errors.push_back(new_string_value(format!(
+                        "{}: missing required key '{}'",
+                        path, k
+                    )));
+                }
+            }
+        }
+        if let Some(properties_rr) = schema.get("properties") {
+            let properties = match properties_rr {
+                Value::Hash(properties) => properties.borrow(),
+                _ => return Err("schema properties must be hash".to_string()),
+            };
+            for (k, property_schema) in properties.iter() {
+                if let Some(child) = map.get(k) {
+                    let child_path = if path == "." {
+                        format!(".{}", k)
+                    } else {
+                        format!("{}.{}", path, k)
+                    };
+                    validate_json(child, property_schema, &child_path, errors)?;
+                }
+            }
+        }
+    }
+
+    if let Value::List(lst) = v {
+        if let Some(items_schema) = schema.get("items") {
+            for (i, child) in lst.borrow().iter().enumerate() {
+                let child_path = format!("{}[{}]", path, i);
+                validate_json(child, items_schema, &child_path, errors)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The source of the bytes for a JSON event parser.
+enum JsonByteSource {
+    Reader(Rc<RefCell<BufReaderWithBuffer<File>>>),
+    Bytes(Vec<u8>, usize),
+}
+
+impl JsonByteSource {
+    /// Returns the next byte from the source, or None at the end of
+    /// the input.
+    fn next_byte(&mut self) -> Result<Option<u8>, String> {
+        match self {
+            JsonByteSource::Reader(reader) => match reader.borrow_mut().read_byte() {
+                Some(b) => Ok(b),
+                None => Err("unable to read from file".to_string()),
+            },
+            JsonByteSource::Bytes(bytes, index) => {
+                let b = bytes.get(*index).copied();
+                *index += 1;
+                Ok(b)
+            }
+        }
+    }
+}
+
+/// The containers that a JSON event parser may be within.
+enum JsonContext {
+    Object,
+    Array,
+}
+
+/// The states of a JSON event parser, indicating what is expected
+/// next in the input.
+#[derive(PartialEq)]
+enum JsonState {
+    Value,
+    ValueOrEnd,
+    KeyOrEnd,
+    Key,
+    Colon,
+    CommaOrEnd,
+    Done,
+}
+
+/// A streaming JSON parser that returns parse events (see
+/// core_json_events), so that the document does not need to be
+/// held in memory.
+struct JsonEventParser {
+    source: JsonByteSource,
+    peeked: Option<u8>,
+    contexts: Vec<JsonContext>,
+    state: JsonState,
+}
+
+impl JsonEventParser {
+    fn new(source: JsonByteSource) -> JsonEventParser {
+        JsonEventParser {
+            source,
+            peeked: None,
+            contexts: Vec::new(),
+            state: JsonState::Value,
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, String> {
+        if self.peeked.is_none() {
+            self.peeked = self.source.next_byte()?;
+        }
+        Ok(self.peeked)
+    }
+
+    fn next(&mut self) -> Result<Option<u8>, String> {
+        match self.peeked.take() {
+            Some(b) => Ok(Some(b)),
+            None => self.source.next_byte(),
+        }
+    }
+
+    fn expect_next(&mut self) -> Result<u8, String> {
+        match self.next()? {
+            Some(b) => Ok(b),
+            None => Err("unexpected end of input".to_string()),
+        }
+    }
+
+    /// Skips whitespace, and returns the next byte without consuming
+    /// it.
+    fn skip_whitespace(&mut self) -> Result<Option<u8>, String> {
+        loop {
+            match self.peek()? {
+                Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') => {
+                    self.next()?;
+                }
+                b => return Ok(b),
+            }
+        }
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32, String> {
+        let mut n = 0;
+        for _ in 0..4 {
+            let b = self.expect_next()?;
+            match (b as char).to_digit(16) {
+                Some(d) => n = n * 16 + d,
+                None => return Err("invalid unicode escape".to_string()),
+            }
+        }
+        Ok(n)
+    }
+
+    /// Parses a string, where the opening double-quote character has
+    /// already been consumed.
+    fn parse_string(&mut self) -> Result<String, String> {
+        let mut bytes = Vec::new();
+        loop {
+            let b = match self.next()? {
+                Some(b) => b,
+                None => return Err("unterminated string".to_string()),
+            };
+            match b {
+                b'"' => break,
+                b'\\' => {
+                    let c = match self.expect_next()? {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => {
+                            let mut n = self.parse_hex4()?;
+                            if (0xD800..0xDC00).contains(&n) {
+                                if self.expect_next()? != b'\\' || self.expect_next()? != b'u' {
+                                    return Err("invalid unicode escape".to_string());
+                                }
+                                let low = self.parse_hex4()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err("invalid unicode escape".to_string());
+                                }
+                                n = 0x10000 + ((n - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            match char::from_u32(n) {
+                                Some(c) => c,
+                                None => return Err("invalid unicode escape".to_string()),
+                            }
+                        }
+                        _ => return Err("invalid escape sequence".to_string()),
+                    };
+                    let mut buf = [0; 4];
+                    bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+                _ => bytes.push(b),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| "invalid UTF-8 in string".to_string())
+    }
+
+    /// Parses a number or a literal (true, false, or null), where the
+    /// first byte has already been consumed.
+    fn parse_scalar(&mut self, first: u8) -> Result<Value, String> {
+        let mut token = String::new();
+        token.push(first as char);
+        while let Some(b) = self.peek()? {
+            if b.is_ascii_alphanumeric() || b == b'+' || b == b'-' || b == b'.' {
+                token.push(b as char);
+                self.next()?;
+            } else {
+                break;
+            }
+        }
+        match token.as_str() {
+            "true" => return Ok(Value::Bool(true)),
+            "false" => return Ok(Value::Bool(false)),
+            "null" => return Ok(Value::Null),
+            _ => {}
+        }
+        let err = Err(format!("invalid token '{}'", token));
+        if !JSON_NUMBER.is_match(&token) {
+            return err;
+        }
+        if token.contains(['.', 'e', 'E']) {
+            match token.parse::<f64>() {
+                Ok(f) => Ok(Value::Float(f)),
+                _ => err,
+            }
+        } else {
+            match token.parse::<BigInt>() {
+                Ok(n) => match n.to_i32() {
+                    Some(i) => Ok(Value::Int(i)),
+                    None => Ok(Value::BigInt(n)),
+                },
+                _ => err,
+            }
+        }
+    }
+
+    /// Updates the parser state after a complete value has been
+    /// parsed.
+    fn after_value(&mut self) {
+        self.state = if self.contexts.is_empty() {
+            JsonState::Done
+        } else {
+            JsonState::CommaOrEnd
+        };
+    }
+
+    /// Returns the next event type and its associated value, or None
+    /// if the input has been fully parsed.
+    fn next_event(&mut self) -> Result<Option<(&'static str, Value)>, String> {
+        loop {
+            let c = self.skip_whitespace()?;
+            if self.state == JsonState::Done {
+                return match c {
+                    None => Ok(None),
+                    Some(_) => Err("unexpected trailing characters".to_string()),
+                };
+            }
+            let c = self.expect_next()?;
+            match self.state {
+                JsonState::Value | JsonState::ValueOrEnd => {
+                    if c == b']' && self.state == JsonState::ValueOrEnd {
+                        self.contexts.pop();
+                        self.after_value();
+                        return Ok(Some(("end-array", Value::Null)));
+                    }
+                    match c {
+                        b'{' => {
+                            self.contexts.push(JsonContext::Object);
+                            self.state = JsonState::KeyOrEnd;
+                            return Ok(Some(("start-object", Value::Null)));
+                        }
+                        b'[' => {
+                            self.contexts.push(JsonContext::Array);
+                            self.state = JsonState::ValueOrEnd;
+                            return Ok(Some(("start-array", Value::Null)));
+                        }
+                        b'"' => {
+                            let s = self.parse_string()?;
+                            self.after_value();
+                            return Ok(Some(("value", new_string_value(s))));
+                        }
+                        b'-' | b'0'..=b'9' | b'a'..=b'z' => {
+                            let v = self.parse_scalar(c)?;
+                            self.after_value();
+                            return Ok(Some(("value", v)));
+                        }
+                        _ => {
+                            return Err(format!("unexpected character '{}'", c as char));
+                        }
+                    }
+                }
+                JsonState::KeyOrEnd | JsonState::Key => {
+                    if c == b'}' && self.state == JsonState::KeyOrEnd {
+                        self.contexts.pop();
+                        self.after_value();
+                        return Ok(Some(("end-object", Value::Null)));
+                    }
+                    if c != b'"' {
+                        return Err("expected object key".to_string());
+                    }
+                    let s = self.parse_string()?;
+                    self.state = JsonState::Colon;
+                    return Ok(Some(("key", new_string_value(s))));
+                }
+                JsonState::Colon => {
+                    if c != b':' {
+                        return Err("expected ':' after object key".to_string());
+                    }
+                    self.state = JsonState::Value;
+                }
+                JsonState::CommaOrEnd => match (c, self.contexts.last()) {
+                    (b',', Some(JsonContext::Object)) => {
+                        self.state = JsonState::Key;
+                    }
+                    (b',', Some(JsonContext::Array)) => {
+                        self.state = JsonState::Value;
+                    }
+                    (b'}', Some(JsonContext::Object)) => {
+                        self.contexts.pop();
+                        self.after_value();
+                        return Ok(Some(("end-object", Value::Null)));
+                    }
+                    (b']', Some(JsonContext::Array)) => {
+                        self.contexts.pop();
+                        self.after_value();
+                        return Ok(Some(("end-array", Value::Null)));
+                    }
+                    _ => {
+                        return Err(format!("unexpected character '{}'", c as char));
+                    }
+                },
+                JsonState::Done => unreachable!(),
+            }
+        }
+    }
+}
+
+/// A single step in a selector expression (see core_select).
+enum SelectorStep {
+    Field(String),
+    Index(i32),
+    Iterate,
+}
+
+/// Parses a quoted field name from a selector expression, where the
+/// opening double-quote character has already been consumed.
+fn parse_selector_string(
+    chars: &mut std::iter::Peekable<std::str::Chars>
+) -> Result<String, String> {
+    let mut name = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => {
+                return Ok(name);
+            }
+            Some('\\') => match chars.next() {
+                Some(c) => name.push(c),
+                None => return Err("unterminated string".to_string()),
+            },
+            Some(c) => name.push(c),
+            None => return Err("unterminated string".to_string()),
+        }
+    }
+}
+
+/// Parses a bracketed selector step, where the opening bracket has
+/// already been consumed.
+fn parse_selector_bracket(
+    chars: &mut std::iter::Peekable<std::str::Chars>
+) -> Result<SelectorStep, String> {
+    let step = match chars.peek() {
+        Some(']') => SelectorStep::Iterate,
+        Some('"') => {
+            chars.next();
+            SelectorStep::Field(parse_selector_string(chars)?)
+        }
+        _ => {
+            let mut index_str = String::new();
+            while let Some(c) = chars.peek() {
+                if *c == ']' {
+                    break;
+                }
+                index_str.push(*c);
+                chars.next();
+            }
+            match index_str.trim().parse::<i32>() {
+                Ok(n) => SelectorStep::Index(n),
+                _ => return Err(format!("invalid index '{}'", index_str)),
+            }
+        }
+    };
+    match chars.next() {
+        Some(']') => Ok(step),
+        _ => Err("expected ']'".to_string()),
+    }
+}
+
+/// Parses a selector expression into a list of steps.  Since each
+/// stage of a pipeline is a path expression, the stages are simply
+/// concatenated.
+fn parse_selector(selector: &str) -> Result<Vec<SelectorStep>, String> {
+    let mut steps = Vec::new();
+    let mut chars = selector.chars().peekable();
+    let mut stage_start = true;
+    loop {
+        while let Some(c) = chars.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            chars.next();
+        }
+        let c = match chars.next() {
+            Some(c) => c,
+            None => {
+                if stage_start {
+                    return Err("expected '.'".to_string());
+                }
+                return Ok(steps);
+            }
+        };
+        match c {
+            '.' => {
+                match chars.peek() {
+                    Some('"') => {
+                        chars.next();
+                        steps.push(SelectorStep::Field(parse_selector_string(&mut chars)?));
+                    }
+                    Some(c) if c.is_alphanumeric() || *c == '_' || *c == '-' => {
+                        let mut name = String::new();
+                        while let Some(c) = chars.peek() {
+                            if !(c.is_alphanumeric() || *c == '_' || *c == '-') {
+                                break;
+                            }
+                            name.push(*c);
+                            chars.next();
+                        }
+                        steps.push(SelectorStep::Field(name));
+                    }
+                    _ => {
+                        if !stage_start {
+                            return Err("expected field name after '.'".to_string());
+                        }
+                    }
+                }
+                stage_start = false;
+            }
+            '[' if !stage_start => {
+                steps.push(parse_selector_bracket(&mut chars)?);
+            }
+            '|' if !stage_start => {
+                stage_start = true;
+            }
+            _ => {
+                return Err(format!("unexpected character '{}'", c));
+            }
+        }
+    }
+}
+
+/// Applies a single selector step to a value, appending the results
+/// to the given list.
+fn apply_selector_step(
+    step: &SelectorStep,
+    value_rr: &Value,
+    results: &mut Vec<Value>,
+) -> Result<(), String> {
+    match (step, value_rr) {
+        (_, Value::Null) => {
+            if let SelectorStep::Iterate = step {
+                return Err("cannot iterate over null".to_string());
+            }
+            results.push(Value::Null);
+        }
+        (SelectorStep::Field(name), Value::Hash(map)) => {
+            results.push(map.borrow().get(name).cloned().unwrap_or(Value::Null));
+        }
+        (SelectorStep::Index(n), Value::List(lst)) => {
+            let lstb = lst.borrow();
+            let index = if *n < 0 { lstb.len() as i32 + *n } else { *n };
+            let value = if index < 0 {
+                None
+            } else {
+                lstb.get(index as usize).cloned()
+            };
+            results.push(value.unwrap_or(Value::Null));
+        }
+        (SelectorStep::Iterate, Value::List(lst)) => {
+            results.extend(lst.borrow().iter().cloned());
+        }
+        (SelectorStep::Iterate, Value::Hash(map)) => {
+            results.extend(map.borrow().values().cloned());
+        }
+        (SelectorStep::Field(name), _) => {
+            return Err(format!(
+                "cannot get field '{}' from {}",
+                name,
+                value_rr.type_string()
+            ));
+        }
+        (SelectorStep::Index(n), _) => {
+            return Err(format!(
+                "cannot get index {} from {}",
+                n,
+                value_rr.type_string()
+            ));
+        }
+        (SelectorStep::Iterate, _) => {
+            return Err(format!("cannot iterate over {}", value_rr.type_string()));
+        }
+    }
+    Ok(())
+}
+
 impl VM {
     /// Takes a JSON string, converts it into a hash, and puts the
     /// result onto the stack.
@@ -154,4 +808,193 @@ impl VM {
         self.stack.push(new_string_value(convert_to_json(&value_rr)));
         1
     }
+
+    /// Takes a value and an options hash as its arguments.  The
+    /// options hash may contain 'order' (a list of keys to put first,
+    /// in that order), 'include' (a list or set of keys to include),
+    /// 'exclude' (a list or set of keys to exclude), and 'recursive'
+    /// (whether to apply the options to nested hashes).  Converts
+    /// the value into a JSON string representation, and puts the
+    /// result onto the stack.
+    pub fn core_to_json_with(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("to-json-with requires two arguments");
+            return 0;
+        }
+
+        let opts_rr = self.stack.pop().unwrap();
+        let value_rr = self.stack.pop().unwrap();
+
+        let opts_map = match opts_rr {
+            Value::Hash(ref map) => map.borrow(),
+            _ => {
+                self.print_error("second to-json-with argument must be hash");
+                return 0;
+            }
+        };
+        let mut opts = JsonKeyOptions {
+            order: Vec::new(),
+            include: None,
+            exclude: Vec::new(),
+            recursive: false,
+        };
+        for (k, v) in opts_map.iter() {
+            match k.as_str() {
+                "recursive" => {
+                    opts.recursive = v.to_bool();
+                    continue;
+                }
+                "order" | "include" | "exclude" => {}
+                _ => {
+                    let err_str = format!("to-json-with option '{}' is not supported", k);
+                    self.print_error(&err_str);
+                    return 0;
+                }
+            }
+            let keys = match value_to_key_list(v) {
+                Some(keys) => keys,
+                _ => {
+                    let err_str = format!("to-json-with option '{}' must be list or set", k);
+                    self.print_error(&err_str);
+                    return 0;
+                }
+            };
+            match k.as_str() {
+                "order" => opts.order = keys,
+                "include" => opts.include = Some(keys),
+                _ => opts.exclude = keys,
+            }
+        }
+
+        let json = convert_to_json_with(&value_rr, &opts, 0);
+        self.stack.push(new_string_value(json));
+        1
+    }
+
+    /// Takes a value (typically the result of from-json) and a
+    /// schema hash as its arguments.  Validates the value against the
+    /// schema, and puts a list of validation error messages onto the
+    /// stack (the list is empty if the value is valid).
+    pub fn core_json_validate(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("json-validate requires two arguments");
+            return 0;
+        }
+
+        let schema_rr = self.stack.pop().unwrap();
+        let value_rr = self.stack.pop().unwrap();
+
+        let mut errors = VecDeque::new();
+        match validate_json(&value_rr, &schema_rr, ".", &mut errors) {
+            Ok(()) => {
+                self.stack.push(Value::List(Rc::new(RefCell::new(errors))));
+                1
+            }
+            Err(e) => {
+                let err_str = format!("json-validate schema is invalid: {}", e);
+                self.print_error(&err_str);
+                0
+            }
+        }
+    }
+
+    /// Takes a value (typically the result of from-json) and a
+    /// selector string as its arguments.  Evaluates the selector
+    /// (which uses a subset of the jq filter syntax) against the
+    /// value, and puts a list of the results onto the stack.
+    pub fn core_select(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("select requires two arguments");
+            return 0;
+        }
+
+        let selector_rr = self.stack.pop().unwrap();
+        let selector_opt: Option<&str>;
+        to_str!(selector_rr, selector_opt);
+        let steps = match selector_opt {
+            Some(s) => match parse_selector(s) {
+                Ok(steps) => steps,
+                Err(e) => {
+                    let err_str = format!("select selector is invalid: {}", e);
+                    self.print_error(&err_str);
+                    return 0;
+                }
+            },
+            _ => {
+                self.print_error("second select argument must be string");
+                return 0;
+            }
+        };
+
+        let value_rr = self.stack.pop().unwrap();
+        let mut values = vec![value_rr];
+        for step in steps.iter() {
+            let mut results = Vec::new();
+            for value_rr in values.iter() {
+                if let Err(e) = apply_selector_step(step, value_rr, &mut results) {
+                    let err_str = format!("select failed: {}", e);
+                    self.print_error(&err_str);
+                    return 0;
+                }
+            }
+            values = results;
+        }
+        let lst = values.into_iter().collect::<VecDeque<Value>>();
+        self.stack.push(Value::List(Rc::new(RefCell::new(lst))));
+        1
+    }
+
+    /// Takes a file reader (or a JSON string) and a callable as its
+    /// arguments.  Parses the JSON document incrementally, and calls
+    /// the callable for each parse event with the event type and its
+    /// associated value.
+    pub fn core_json_events(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("json-events requires two arguments");
+            return 0;
+        }
+
+        let fn_rr = self.stack.pop().unwrap();
+        if !fn_rr.is_callable() {
+            self.print_error("second json-events argument must be callable");
+            return 0;
+        }
+
+        let input_rr = self.stack.pop().unwrap();
+        let source = match input_rr {
+            Value::FileReader(ref reader) => JsonByteSource::Reader(reader.clone()),
+            _ => {
+                let input_opt: Option<&str>;
+                to_str!(input_rr, input_opt);
+                match input_opt {
+                    Some(s) => JsonByteSource::Bytes(s.as_bytes().to_vec(), 0),
+                    _ => {
+                        self.print_error("first json-events argument must be file reader or string");
+                        return 0;
+                    }
+                }
+            }
+        };
+
+        let mut parser = JsonEventParser::new(source);
+        loop {
+            match parser.next_event() {
+                Ok(Some((event, value))) => {
+                    self.stack.push(new_string_value(event.to_string()));
+                    self.stack.push(value);
+                    if !self.call(OpCode::Call, fn_rr.clone()) {
+                        return 0;
+                    }
+                }
+                Ok(None) => {
+                    return 1;
+                }
+                Err(e) => {
+                    let err_str = format!("json-events input is not valid JSON: {}", e);
+                    self.print_error(&err_str);
+                    return 0;
+                }
+            }
+        }
+    }
 }
@@ -7,10 +7,13 @@ use std::thread;
 use std::time;
 
 use indexmap::IndexMap;
+use rand::seq::{IteratorRandom, SliceRandom};
+use rand::Rng;
 
 use crate::chunk::{IpSet, Value, ValueSD,
                    valuesd_to_value, read_valuesd,
                    new_string_value};
+use crate::opcode::OpCode;
 use crate::vm::VM;
 
 impl VM {
@@ -458,6 +461,19 @@ impl VM {
                     }
                 }
             }
+            Value::ReverseFileReader(ref mut rfr) => {
+                let str_res = rfr.borrow_mut().readline();
+
+                match str_res {
+                    Some(v) => {
+                        self.stack.push(v);
+                    }
+                    _ => {
+                        self.print_error("unable to read from file");
+                        return 0;
+                    }
+                }
+            }
             Value::TcpSocketReader(ref mut brwb) => {
                 loop {
                     let str_res = brwb.borrow_mut().readline();
@@ -707,4 +723,356 @@ impl VM {
         }
         1
     }
+
+    /// Takes a shiftable object and an index as its arguments.  Puts
+    /// a list containing the elements before the index onto the
+    /// stack, followed by the remaining elements.  If the object is a
+    /// list, then the remainder is a new list.  Otherwise, the
+    /// remainder is the original object, after the initial elements
+    /// have been shifted from it.
+    pub fn core_split_at(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("split-at requires two arguments");
+            return 0;
+        }
+
+        let index_rr = self.stack.pop().unwrap();
+        let index = match index_rr.to_int() {
+            Some(n) if n >= 0 => n as usize,
+            _ => {
+                self.print_error("second split-at argument must be non-negative integer");
+                return 0;
+            }
+        };
+
+        let shiftable_rr = self.stack.pop().unwrap();
+        if let Value::List(lst) = shiftable_rr {
+            let mut first = lst.borrow().clone();
+            let rest = first.split_off(index.min(first.len()));
+            self.stack.push(Value::List(Rc::new(RefCell::new(first))));
+            self.stack.push(Value::List(Rc::new(RefCell::new(rest))));
+            return 1;
+        }
+        if !shiftable_rr.is_shiftable() {
+            self.print_error("first split-at argument must be shiftable");
+            return 0;
+        }
+
+        let mut first = VecDeque::new();
+        while first.len() < index {
+            self.stack.push(shiftable_rr.clone());
+            let shift_res = self.opcode_shift();
+            if shift_res == 0 {
+                return 0;
+            }
+            let value_rr = self.stack.pop().unwrap();
+            if let Value::Null = value_rr {
+                break;
+            }
+            first.push_back(value_rr);
+        }
+        self.stack.push(Value::List(Rc::new(RefCell::new(first))));
+        self.stack.push(shiftable_rr);
+        1
+    }
+
+    /// Helper function for position and positions.  Takes a list (or
+    /// generator) and a target value or predicate function as its
+    /// arguments.  If the second argument is a function (and not a
+    /// string), then an element matches if the function returns a
+    /// true value for it.  Otherwise, an element matches if it is
+    /// equal to the target.  Returns the indexes of the matching
+    /// elements, stopping after the first match if `all` is false.
+    fn positions_inner(&mut self, name: &str, all: bool) -> Option<Vec<i32>> {
+        if self.stack.len() < 2 {
+            let err_str = format!("{} requires two arguments", name);
+            self.print_error(&err_str);
+            return None;
+        }
+
+        let target_rr = self.stack.pop().unwrap();
+        let mut lst_rr = self.stack.pop().unwrap();
+        if lst_rr.is_generator() {
+            self.stack.push(lst_rr);
+            let res = self.generator_to_list();
+            if res == 0 {
+                return None;
+            }
+            lst_rr = self.stack.pop().unwrap();
+        }
+        let elements = match lst_rr {
+            Value::List(lst) => lst.borrow().iter().cloned().collect::<Vec<Value>>(),
+            _ => {
+                let err_str = format!("first {} argument must be list", name);
+                self.print_error(&err_str);
+                return None;
+            }
+        };
+
+        let is_predicate = matches!(
+            target_rr,
+            Value::AnonymousFunction(_, _)
+                | Value::CoreFunction(_)
+                | Value::NamedFunction(_)
+        );
+
+        let mut indexes = Vec::new();
+        for (i, element_rr) in elements.iter().enumerate() {
+            let is_match = if is_predicate {
+                self.stack.push(element_rr.clone());
+                let res = self.call(OpCode::Call, target_rr.clone());
+                if !res {
+                    return None;
+                }
+                if self.stack.is_empty() {
+                    let err_str = format!("{} predicate should return a value", name);
+                    self.print_error(&err_str);
+                    return None;
+                }
+                self.stack.pop().unwrap().to_bool()
+            } else {
+                self.opcode_eq_inner(element_rr, &target_rr) == 1
+            };
+            if is_match {
+                indexes.push(i as i32);
+                if !all {
+                    break;
+                }
+            }
+        }
+        Some(indexes)
+    }
+
+    /// Takes a list and a target value or predicate function as its
+    /// arguments.  Puts the index of the first matching element onto
+    /// the stack, or -1 if there is no such element.
+    pub fn core_position(&mut self) -> i32 {
+        match self.positions_inner("position", false) {
+            Some(indexes) => {
+                let index = indexes.first().copied().unwrap_or(-1);
+                self.stack.push(Value::Int(index));
+                1
+            }
+            _ => 0,
+        }
+    }
+
+    /// Takes a list and a target value or predicate function as its
+    /// arguments.  Puts a list containing the indexes of all of the
+    /// matching elements onto the stack.
+    pub fn core_positions(&mut self) -> i32 {
+        match self.positions_inner("positions", true) {
+            Some(indexes) => {
+                let lst = indexes
+                    .into_iter()
+                    .map(Value::Int)
+                    .collect::<VecDeque<Value>>();
+                self.stack.push(Value::List(Rc::new(RefCell::new(lst))));
+                1
+            }
+            _ => 0,
+        }
+    }
+
+    /// Helper function for head and tail.  Takes a shiftable object
+    /// or a file path and a count as its arguments, and returns the
+    /// count and the object (opening the file, in the case of a
+    /// path).
+    fn head_tail_args(&mut self, name: &str) -> Option<(usize, Value)> {
+        if self.stack.len() < 2 {
+            let err_str = format!("{} requires two arguments", name);
+            self.print_error(&err_str);
+            return None;
+        }
+
+        let n_rr = self.stack.pop().unwrap();
+        let n = match n_rr.to_int() {
+            Some(n) if n >= 0 => n as usize,
+            _ => {
+                let err_str = format!("second {} argument must be non-negative integer", name);
+                self.print_error(&err_str);
+                return None;
+            }
+        };
+
+        let object_rr = self.stack.pop().unwrap();
+        let arg_desc = format!("first {} argument", name);
+        self.shiftable_or_path(&arg_desc, object_rr).map(|v| (n, v))
+    }
+
+    /// Returns the argument value if it is shiftable, or a file
+    /// reader for the argument value if it is a string.
+    fn shiftable_or_path(&mut self, arg_desc: &str,
+                         object_rr: Value) -> Option<Value> {
+        if object_rr.is_shiftable() {
+            return Some(object_rr);
+        }
+        if let Value::String(_) = object_rr {
+            self.stack.push(object_rr);
+            self.stack.push(new_string_value("r".to_string()));
+            let res = self.opcode_open();
+            if res == 0 {
+                return None;
+            }
+            return self.stack.pop();
+        }
+        let err_str = format!("{} must be shiftable or file path", arg_desc);
+        self.print_error(&err_str);
+        None
+    }
+
+    /// Takes a shiftable object (or a file path) and a count as its
+    /// arguments.  Puts a list containing the first count elements
+    /// (or lines) onto the stack.  Only the required number of
+    /// elements are shifted from the object.
+    pub fn core_head(&mut self) -> i32 {
+        let (n, object_rr) = match self.head_tail_args("head") {
+            Some(args) => args,
+            _ => return 0,
+        };
+
+        let mut results = VecDeque::new();
+        if let Value::List(lst) = object_rr {
+            results.extend(lst.borrow().iter().take(n).cloned());
+        } else {
+            while results.len() < n {
+                self.stack.push(object_rr.clone());
+                let res = self.opcode_shift();
+                if res == 0 {
+                    return 0;
+                }
+                let value_rr = self.stack.pop().unwrap();
+                if let Value::Null = value_rr {
+                    break;
+                }
+                results.push_back(value_rr);
+            }
+        }
+        self.stack.push(Value::List(Rc::new(RefCell::new(results))));
+        1
+    }
+
+    /// Takes a shiftable object (or a file path) and a count as its
+    /// arguments.  Puts a list containing the last count elements (or
+    /// lines) onto the stack.  Only the last count elements are kept
+    /// in memory while the object is being processed.
+    pub fn core_tail(&mut self) -> i32 {
+        let (n, object_rr) = match self.head_tail_args("tail") {
+            Some(args) => args,
+            _ => return 0,
+        };
+
+        let mut results = VecDeque::new();
+        if let Value::List(lst) = object_rr {
+            let lstb = lst.borrow();
+            let start = lstb.len().saturating_sub(n);
+            results.extend(lstb.iter().skip(start).cloned());
+        } else {
+            loop {
+                self.stack.push(object_rr.clone());
+                let res = self.opcode_shift();
+                if res == 0 {
+                    return 0;
+                }
+                let value_rr = self.stack.pop().unwrap();
+                if let Value::Null = value_rr {
+                    break;
+                }
+                if n == 0 {
+                    continue;
+                }
+                if results.len() == n {
+                    results.pop_front();
+                }
+                results.push_back(value_rr);
+            }
+        }
+        self.stack.push(Value::List(Rc::new(RefCell::new(results))));
+        1
+    }
+
+    /// Takes a shiftable object (or a file path) as its single
+    /// argument.  Puts a list containing all of the elements (or
+    /// lines) from the object, in random order, onto the stack.
+    pub fn core_shuf(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("shuf requires one argument");
+            return 0;
+        }
+
+        let object_rr = self.stack.pop().unwrap();
+        let object_rr = match self.shiftable_or_path("shuf argument", object_rr) {
+            Some(object_rr) => object_rr,
+            _ => return 0,
+        };
+
+        let mut results = Vec::new();
+        if let Value::List(lst) = object_rr {
+            results.extend(lst.borrow().iter().cloned());
+        } else {
+            loop {
+                self.stack.push(object_rr.clone());
+                let res = self.opcode_shift();
+                if res == 0 {
+                    return 0;
+                }
+                let value_rr = self.stack.pop().unwrap();
+                if let Value::Null = value_rr {
+                    break;
+                }
+                results.push(value_rr);
+            }
+        }
+        results.shuffle(&mut rand::thread_rng());
+        self.stack.push(Value::List(Rc::new(RefCell::new(
+            results.into_iter().collect(),
+        ))));
+        1
+    }
+
+    /// Takes a shiftable object (or a file path) and a count as its
+    /// arguments.  Puts a list containing count elements (or lines)
+    /// selected at random from the object onto the stack.  Reservoir
+    /// sampling is used, so only count elements are held in memory
+    /// at any one time.
+    pub fn core_shuf_n(&mut self) -> i32 {
+        let (n, object_rr) = match self.head_tail_args("shuf-n") {
+            Some(args) => args,
+            _ => return 0,
+        };
+
+        let mut rng = rand::thread_rng();
+        let mut results = Vec::new();
+        if let Value::List(lst) = object_rr {
+            results =
+                lst.borrow().iter().cloned().choose_multiple(&mut rng, n);
+        } else {
+            let mut seen = 0;
+            loop {
+                self.stack.push(object_rr.clone());
+                let res = self.opcode_shift();
+                if res == 0 {
+                    return 0;
+                }
+                let value_rr = self.stack.pop().unwrap();
+                if let Value::Null = value_rr {
+                    break;
+                }
+                seen += 1;
+                if results.len() < n {
+                    results.push(value_rr);
+                } else {
+                    let index = rng.gen_range(0..seen);
+                    if index < n {
+                        results[index] = value_rr;
+                    }
+                }
+            }
+        }
+        results.shuffle(&mut rng);
+        self.stack.push(Value::List(Rc::new(RefCell::new(
+            results.into_iter().collect(),
+        ))));
+        1
+    }
 }
@@ -11,6 +11,37 @@ use unicode_segmentation::UnicodeSegmentation;
 use crate::chunk::{Chunk, Value};
 use crate::vm::*;
 
+/// Writes a string to standard output, flushing it afterwards if
+/// `autoflush` is set.  If standard output has been closed by the
+/// reader (e.g. when the output is being piped to `head`), then the
+/// process exits quietly with a success status.
+fn print_stdout(s: &str, autoflush: bool) {
+    let mut stdout = io::stdout();
+    let mut res = stdout.write_all(s.as_bytes());
+    if res.is_ok() && autoflush {
+        res = stdout.flush();
+    }
+    handle_stdout_result(res);
+}
+
+/// Handles the result of a write to standard output, per
+/// print_stdout.
+fn handle_stdout_result(res: io::Result<()>) {
+    if let Err(e) = res {
+        if e.kind() == io::ErrorKind::BrokenPipe {
+            std::process::exit(0);
+        }
+        panic!("failed printing to stdout: {}", e);
+    }
+}
+
+/// Writes a string followed by a newline to standard output, per
+/// print_stdout.
+fn println_stdout(s: &str, autoflush: bool) {
+    print_stdout(s, autoflush);
+    print_stdout("\n", autoflush);
+}
+
 /// Helper function for paging once the line limit has been reached.
 fn pager_input(window_height: i32,
                mut lines_to_print: i32) -> i32 {
@@ -82,6 +113,7 @@ fn pager_input(window_height: i32,
 /// cancels further output.  (A window height of zero indicates that
 /// the current program is not being run interactively, in which case
 /// no waiting is required.)
+#[allow(clippy::too_many_arguments)]
 fn psv_helper(
     s: &str,
     indent: i32,
@@ -90,17 +122,18 @@ fn psv_helper(
     window_width: i32,
     mut lines_to_print: i32,
     index: Option<i32>,
+    autoflush: bool,
 ) -> i32 {
     if !atty::is(Stream::Stdout) || (window_width == 0) {
         if !no_first_indent {
             for _ in 0..indent {
-                print!(" ");
+                print_stdout(" ", autoflush);
             }
         }
         if let Some(n) = index {
-            print!("{}: ", n);
+            print_stdout(&format!("{}: ", n), autoflush);
         }
-        println!("{}", s);
+        println_stdout(s, autoflush);
         return lines_to_print - 1;
     }
 
@@ -113,13 +146,13 @@ fn psv_helper(
     if !no_first_indent {
         str_offset += indent;
         for _ in 0..indent {
-            print!(" ");
+            print_stdout(" ", autoflush);
         }
     }
     if let Some(n) = index {
         str_offset += n.to_string().len() as i32;
         str_offset += 2;
-        print!("{}: ", n);
+        print_stdout(&format!("{}: ", n), autoflush);
     }
 
     let mut str_finish =
@@ -129,13 +162,13 @@ fn psv_helper(
 
     let slen = graphemes.len();
     if slen < str_finish {
-        println!("{}", s);
+        println_stdout(s, autoflush);
         return lines_to_print - 1;
     }
     let mut str_start = 0;
     while str_finish < slen {
         let joined_str = graphemes[str_start..str_finish].join("");
-        println!("{}", joined_str);
+        println_stdout(&joined_str, autoflush);
         str_start = str_finish;
         str_finish += window_width as usize;
         lines_to_print -= 1;
@@ -146,13 +179,71 @@ fn psv_helper(
     }
     if str_start <= slen {
         let joined_str = graphemes[str_start..slen].join("");
-        println!("{}", joined_str);
+        println_stdout(&joined_str, autoflush);
         lines_to_print -= 1;
     }
 
     return lines_to_print;
 }
 
+/// The maximum container nesting depth that is printed by
+/// debug-stack.
+const DEBUG_STACK_DEPTH: usize = 4;
+
+/// Helper function for core_debug_stack.  Takes a value, an indent
+/// count, the current nesting depth, and an optional label (an index
+/// or a hash key) as its arguments.  Prints the value's type and
+/// representation to standard error, recursing into lists, hashes
+/// and sets until the depth limit is reached.
+fn debug_stack_value(value_rr: &Value, indent: usize, depth: usize,
+                     label: Option<String>) {
+    let prefix = match label {
+        Some(s) => format!("{}{}: ", " ".repeat(indent), s),
+        None => " ".repeat(indent),
+    };
+    let type_string = value_rr.type_string();
+    match value_rr {
+        Value::List(lst) => {
+            let lb = lst.borrow();
+            if lb.is_empty() {
+                eprintln!("{}{} ()", prefix, type_string);
+            } else if depth >= DEBUG_STACK_DEPTH {
+                eprintln!("{}{} (...)", prefix, type_string);
+            } else {
+                eprintln!("{}{} (", prefix, type_string);
+                for (i, element) in lb.iter().enumerate() {
+                    debug_stack_value(element, indent + 4, depth + 1,
+                                      Some(i.to_string()));
+                }
+                eprintln!("{})", " ".repeat(indent));
+            }
+        }
+        Value::Hash(map) | Value::Set(map) => {
+            let is_hash = matches!(value_rr, Value::Hash(_));
+            let mb = map.borrow();
+            if mb.is_empty() {
+                eprintln!("{}{} ()", prefix, type_string);
+            } else if depth >= DEBUG_STACK_DEPTH {
+                eprintln!("{}{} (...)", prefix, type_string);
+            } else {
+                eprintln!("{}{} (", prefix, type_string);
+                for (k, v) in mb.iter() {
+                    let label = if is_hash {
+                        Some(format!("\"{}\"", k))
+                    } else {
+                        None
+                    };
+                    debug_stack_value(v, indent + 4, depth + 1, label);
+                }
+                eprintln!("{})", " ".repeat(indent));
+            }
+        }
+        _ => {
+            eprintln!("{}{} {:?}", prefix, type_string, value_rr);
+        }
+    }
+}
+
 impl VM {
     /// Takes a value that can be stringified as its single argument,
     /// and prints that value to standard output.
@@ -168,7 +259,7 @@ impl VM {
 
         match value_opt {
             Some(s) => {
-                print!("{}", s);
+                print_stdout(s, self.autoflush);
                 1
             }
             _ => {
@@ -192,7 +283,7 @@ impl VM {
 
         match value_opt {
             Some(s) => {
-                println!("{}", s);
+                println_stdout(s, self.autoflush);
                 1
             }
             _ => {
@@ -202,6 +293,116 @@ impl VM {
         }
     }
 
+    /// Flushes standard output, so that any output that has been
+    /// buffered (e.g. by print, without a trailing newline) is
+    /// written immediately.
+    pub fn core_flush(&mut self) -> i32 {
+        handle_stdout_result(io::stdout().flush());
+        1
+    }
+
+    /// Takes a boolean as its single argument.  If the boolean is
+    /// true, then standard output is flushed after each write.
+    /// Otherwise, standard output is line-buffered (the default).
+    pub fn core_autoflush(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("autoflush requires one argument");
+            return 0;
+        }
+
+        let value_rr = self.stack.pop().unwrap();
+        let autoflush = value_rr.to_bool();
+        self.autoflush = autoflush;
+        if autoflush {
+            handle_stdout_result(io::stdout().flush());
+        }
+        1
+    }
+
+    /// Takes a current count and a total count as its arguments.
+    /// If standard error is a terminal, then draws a progress bar
+    /// (or, if the total is zero, a spinner) for those counts on
+    /// standard error, replacing any earlier progress output.
+    pub fn core_progress(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("progress requires two arguments");
+            return 0;
+        }
+
+        let total_rr = self.stack.pop().unwrap();
+        let current_rr = self.stack.pop().unwrap();
+
+        let total = match total_rr.to_int() {
+            Some(n) if n >= 0 => n,
+            _ => {
+                self.print_error("second progress argument must be non-negative integer");
+                return 0;
+            }
+        };
+        let current = match current_rr.to_int() {
+            Some(n) if n >= 0 => n,
+            _ => {
+                self.print_error("first progress argument must be non-negative integer");
+                return 0;
+            }
+        };
+
+        if !atty::is(Stream::Stderr) {
+            return 1;
+        }
+        let width = match term_size::dimensions_stderr() {
+            Some((w, _)) => w,
+            _ => 80,
+        };
+
+        let line = if total == 0 {
+            let spinner = ['|', '/', '-', '\\'];
+            format!("{} {}", spinner[(current % 4) as usize], current)
+        } else {
+            let current = std::cmp::min(current, total);
+            let suffix = format!(" {:3}% ({}/{})",
+                                 (current as i64 * 100) / total as i64,
+                                 current, total);
+            let bar_width =
+                std::cmp::max(width.saturating_sub(suffix.len() + 3), 10);
+            let filled =
+                (current as i64 * bar_width as i64 / total as i64) as usize;
+            format!("[{}{}]{}", "=".repeat(filled),
+                    " ".repeat(bar_width - filled), suffix)
+        };
+        let mut stderr = io::stderr();
+        let _ = write!(stderr, "\r{}", line);
+        let _ = stderr.flush();
+        1
+    }
+
+    /// Clears any progress output that has been written by way of
+    /// progress.
+    pub fn core_progress_done(&mut self) -> i32 {
+        if !atty::is(Stream::Stderr) {
+            return 1;
+        }
+        let width = match term_size::dimensions_stderr() {
+            Some((w, _)) => w,
+            _ => 80,
+        };
+        let mut stderr = io::stderr();
+        let _ = write!(stderr, "\r{}\r", " ".repeat(width.saturating_sub(1)));
+        let _ = stderr.flush();
+        1
+    }
+
+    /// Prints each element of the stack to standard error, along with
+    /// its index and type.  Unlike .s, this does not shift any
+    /// generators or otherwise change the stack, so it can be used
+    /// in the middle of a pipeline.
+    pub fn core_debug_stack(&mut self) -> i32 {
+        for (i, value_rr) in self.stack.iter().enumerate() {
+            debug_stack_value(value_rr, 0, 0, Some(i.to_string()));
+        }
+        1
+    }
+
     /// Used by print_stack to print a single stack value.  Takes a
     /// wrapped value, the current chunk, the instruction index, the
     /// map of global functions, the current indent, the window height
@@ -245,6 +446,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::DateTimeNT(dt) => {
@@ -258,6 +460,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::DateTimeOT(dt) => {
@@ -271,6 +474,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 /* The way this works is less than ideal, what with it
@@ -288,6 +492,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::Null => {
@@ -300,6 +505,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::Bool(b) => {
@@ -313,6 +519,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::Byte(b) => {
@@ -326,6 +533,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::Int(n) => {
@@ -339,6 +547,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::BigInt(n) => {
@@ -352,6 +561,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::Float(f) => {
@@ -365,6 +575,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::String(st) => {
@@ -387,6 +598,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::Command(s, _) => {
@@ -400,6 +612,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::CommandUncaptured(s) => {
@@ -413,6 +626,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::FileWriter(_) | Value::DirectoryHandle(_) => {
@@ -426,6 +640,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::AnonymousFunction(_, _) => {
@@ -439,6 +654,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::DBConnectionMySQL(..) => {
@@ -452,6 +668,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::DBConnectionPostgres(..) => {
@@ -465,6 +682,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::DBConnectionSQLite(..) => {
@@ -478,6 +696,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::DBStatementMySQL(..) => {
@@ -491,6 +710,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::DBStatementPostgres(..) => {
@@ -504,6 +724,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::DBStatementSQLite(..) => {
@@ -517,6 +738,7 @@ impl VM {
                         window_width,
                         lines_to_print,
                         index,
+                        self.autoflush,
                     );
                 }
                 Value::List(list) => {
@@ -531,6 +753,7 @@ impl VM {
                             window_width,
                             lines_to_print,
                             index,
+                            self.autoflush,
                         );
                     } else {
                         lines_to_print = psv_helper(
@@ -541,6 +764,7 @@ impl VM {
                             window_width,
                             lines_to_print,
                             index,
+                            self.autoflush,
                         );
                         if lines_to_print == -1 {
                             last_stack.push(Value::List(Rc::new(RefCell::new(VecDeque::from(sublist)))));
@@ -589,7 +813,7 @@ impl VM {
                         last_stack.push(Value::List(Rc::new(RefCell::new(VecDeque::from(sublist)))));
                         lines_to_print =
                             psv_helper(")", indent, false, window_height,
-                                       window_width, lines_to_print, None);
+                                       window_width, lines_to_print, None, self.autoflush);
                     }
                 }
                 Value::Hash(map) => {
@@ -604,6 +828,7 @@ impl VM {
                             window_width,
                             lines_to_print,
                             index,
+                            self.autoflush,
                         );
                     } else {
                         lines_to_print = psv_helper(
@@ -614,6 +839,7 @@ impl VM {
                             window_width,
                             lines_to_print,
                             index,
+                            self.autoflush,
                         );
                         if lines_to_print == -1 {
                             last_stack.push(Value::Hash(Rc::new(RefCell::new(subhash))));
@@ -637,12 +863,12 @@ impl VM {
                             match next {
                                 Some((k, v)) => {
                                     for _ in 0..new_indent {
-                                        print!(" ");
+                                        print_stdout(" ", self.autoflush);
                                     }
-                                    print!("\"{}\": ", k);
+                                    print_stdout(&format!("\"{}\": ", k), self.autoflush);
                                     let extra_spaces = key_maxlen - k.len();
                                     for _ in 0..extra_spaces {
-                                        print!(" ");
+                                        print_stdout(" ", self.autoflush);
                                     }
 
                                     lines_to_print = self.print_stack_value(
@@ -684,7 +910,7 @@ impl VM {
                         last_stack.push(Value::Hash(Rc::new(RefCell::new(subhash))));
                         lines_to_print =
                             psv_helper(")", indent, false, window_height,
-                                       window_width, lines_to_print, None);
+                                       window_width, lines_to_print, None, self.autoflush);
                     }
                 }
                 Value::Set(map) => {
@@ -699,6 +925,7 @@ impl VM {
                             window_width,
                             lines_to_print,
                             index,
+                            self.autoflush,
                         );
                     } else {
                         lines_to_print = psv_helper(
@@ -709,6 +936,7 @@ impl VM {
                             window_width,
                             lines_to_print,
                             index,
+                            self.autoflush,
                         );
                         if lines_to_print == -1 {
                             last_stack.push(Value::Set(Rc::new(RefCell::new(subhash))));
@@ -762,7 +990,7 @@ impl VM {
                         last_stack.push(Value::Set(Rc::new(RefCell::new(subhash))));
                         lines_to_print =
                             psv_helper(")", indent, false, window_height,
-                                       window_width, lines_to_print, None);
+                                       window_width, lines_to_print, None, self.autoflush);
                     }
                 }
                 _ => {
@@ -827,6 +1055,7 @@ impl VM {
                             window_width,
                             lines_to_print,
                             index,
+                            self.autoflush,
                         );
                         if lines_to_print == -1 {
                             let mut submg = VecDeque::new();
@@ -880,11 +1109,12 @@ impl VM {
                     window_width,
                     lines_to_print,
                     index,
+                    self.autoflush,
                 );
             } else {
                 lines_to_print =
                     psv_helper(")]", indent, false, window_height,
-                               window_width, lines_to_print, None);
+                               window_width, lines_to_print, None, self.autoflush);
             }
             last_stack.push(Value::List(Rc::new(RefCell::new(VecDeque::from(sublist)))));
         }
@@ -168,4 +168,51 @@ impl VM {
         }
         1
     }
+
+    /// Takes a value that can be stringified and a list of regex
+    /// strings as its arguments.  Puts a boolean onto the stack
+    /// indicating whether the value matches any of the regexes.
+    pub fn core_matches_any(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("matches-any requires two arguments");
+            return 0;
+        }
+
+        let regexes_rr = self.stack.pop().unwrap();
+        let regexes = match regexes_rr {
+            Value::List(ref lst) => lst.borrow().iter().cloned().collect::<Vec<Value>>(),
+            _ => {
+                self.print_error("second matches-any argument must be list");
+                return 0;
+            }
+        };
+
+        let str_rr = self.stack.pop().unwrap();
+        let str_opt: Option<&str>;
+        to_str!(str_rr, str_opt);
+        match str_opt {
+            Some(s) => {
+                for regex_rr in regexes {
+                    let regex_opt = self.gen_regex(regex_rr);
+                    match regex_opt {
+                        Some((regex, _)) => {
+                            if regex.is_match(s) {
+                                self.stack.push(Value::Bool(true));
+                                return 1;
+                            }
+                        }
+                        _ => {
+                            return 0;
+                        }
+                    }
+                }
+                self.stack.push(Value::Bool(false));
+            }
+            _ => {
+                self.print_error("first matches-any argument must be string");
+                return 0;
+            }
+        }
+        1
+    }
 }
@@ -2,15 +2,276 @@ use std::cell::RefCell;
 use std::collections::VecDeque;
 use std::rc::Rc;
 
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+use base64::engine::DecodePaddingMode;
+use base64::Engine;
 use lazy_static::lazy_static;
+use num::{FromPrimitive, ToPrimitive};
+use num_bigint::BigInt;
 use regex::Regex;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 use crate::chunk::Value;
 use crate::vm::*;
 
+/// The unit suffixes used by human-size and parse-size.
+const SIZE_UNITS: [&str; 9] = ["", "K", "M", "G", "T", "P", "E", "Z", "Y"];
+
+/// Returns a base64 engine for decoding, which accepts input with or
+/// without padding.
+fn base64_decoder(url_safe: bool) -> GeneralPurpose {
+    let config = GeneralPurposeConfig::new()
+        .with_decode_padding_mode(DecodePaddingMode::Indifferent);
+    if url_safe {
+        GeneralPurpose::new(&alphabet::URL_SAFE, config)
+    } else {
+        GeneralPurpose::new(&alphabet::STANDARD, config)
+    }
+}
+
+/// The words used by number-words for numbers below twenty.
+const SMALL_NUMBER_WORDS: [&str; 20] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven",
+    "eight", "nine", "ten", "eleven", "twelve", "thirteen", "fourteen",
+    "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+];
+
+/// The words used by number-words for multiples of ten.
+const TENS_WORDS: [&str; 10] = [
+    "", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy",
+    "eighty", "ninety",
+];
+
+/// The words used by number-words for powers of one thousand.
+const SCALE_WORDS: [&str; 13] = [
+    "", "thousand", "million", "billion", "trillion", "quadrillion",
+    "quintillion", "sextillion", "septillion", "octillion", "nonillion",
+    "decillion", "undecillion",
+];
+
+/// Returns the words for a number between 1 and 999.
+fn hundreds_to_words(n: u128) -> String {
+    let mut parts = Vec::new();
+    if n >= 100 {
+        parts.push(format!("{} hundred", SMALL_NUMBER_WORDS[(n / 100) as usize]));
+    }
+    let rest = (n % 100) as usize;
+    if rest >= 20 {
+        if rest.is_multiple_of(10) {
+            parts.push(TENS_WORDS[rest / 10].to_string());
+        } else {
+            parts.push(format!("{}-{}", TENS_WORDS[rest / 10],
+                               SMALL_NUMBER_WORDS[rest % 10]));
+        }
+    } else if rest > 0 {
+        parts.push(SMALL_NUMBER_WORDS[rest].to_string());
+    }
+    parts.join(" ")
+}
+
+/// Returns the words for a number.
+fn number_to_words(n: i128) -> String {
+    if n == 0 {
+        return SMALL_NUMBER_WORDS[0].to_string();
+    }
+    let mut value = n.unsigned_abs();
+    let mut groups = Vec::new();
+    let mut scale = 0;
+    while value > 0 {
+        let group = value % 1000;
+        if group > 0 {
+            let words = hundreds_to_words(group);
+            if scale > 0 {
+                groups.push(format!("{} {}", words, SCALE_WORDS[scale]));
+            } else {
+                groups.push(words);
+            }
+        }
+        value /= 1000;
+        scale += 1;
+    }
+    groups.reverse();
+    let words = groups.join(" ");
+    if n < 0 {
+        format!("negative {}", words)
+    } else {
+        words
+    }
+}
+
+/// Returns the number of terminal columns needed to display the
+/// string.  The string should not contain ANSI escape sequences.
+fn display_width(s: &str) -> usize {
+    s.graphemes(true).map(|g| std::cmp::min(g.width(), 2)).sum()
+}
+
+/// Truncates the string so that it fits within the given display
+/// width, appending the ellipsis if truncation was necessary.  ANSI
+/// escape sequences are removed if truncation is necessary.
+fn truncate_to_width(s: &str, ellipsis: &str, max_width: usize) -> String {
+    let stripped = ANSI_ESCAPE.replace_all(s, "");
+    if display_width(&stripped) <= max_width {
+        return s.to_string();
+    }
+    let (target, suffix) = if display_width(ellipsis) <= max_width {
+        (max_width - display_width(ellipsis), ellipsis)
+    } else {
+        (max_width, "")
+    };
+    let mut result = String::new();
+    let mut width = 0;
+    for g in stripped.graphemes(true) {
+        let gw = std::cmp::min(g.width(), 2);
+        if width + gw > target {
+            break;
+        }
+        width += gw;
+        result.push_str(g);
+    }
+    result.push_str(suffix);
+    result
+}
+
+/// Returns the characters (in addition to the unreserved characters)
+/// that are left unencoded by uri-encode for the given mode, or None
+/// if the mode is invalid.
+fn uri_encode_extra_chars(mode: &str) -> Option<&'static str> {
+    match mode {
+        "path" => Some("!$&'()*+,;=:@"),
+        "query" => Some("!$'()*,;:@/?"),
+        "full" => Some(":/?#[]@!$&'()*+,;="),
+        _ => None,
+    }
+}
+
+/// Percent-encodes a string, leaving the unreserved characters and
+/// the given extra characters unencoded.
+fn uri_encode(s: &str, extra: &str) -> String {
+    let mut result = String::new();
+    for b in s.bytes() {
+        let c = b as char;
+        if c.is_ascii_alphanumeric() || "-._~".contains(c)
+                || (c.is_ascii() && extra.contains(c)) {
+            result.push(c);
+        } else {
+            result.push_str(&format!("%{:02X}", b));
+        }
+    }
+    result
+}
+
+/// Decodes a percent-encoded string.  Returns None if the string
+/// contains an invalid percent sequence, or if the decoded bytes are
+/// not valid UTF-8.
+fn uri_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes.get(i + 1..i + 3)?;
+            if !hex.iter().all(|h| h.is_ascii_hexdigit()) {
+                return None;
+            }
+            let hex_str = std::str::from_utf8(hex).ok()?;
+            result.push(u8::from_str_radix(hex_str, 16).ok()?);
+            i += 3;
+        } else {
+            result.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(result).ok()
+}
+
+/// Matches a bracket expression (e.g. "[a-z]") from a glob pattern
+/// against a character.  Returns whether the character matched and
+/// the index of the pattern character after the expression, or None
+/// if the expression is not terminated.
+fn glob_match_bracket(pattern: &[char], mut pi: usize, c: char) -> Option<(bool, usize)> {
+    let negated = matches!(pattern.get(pi), Some('!') | Some('^'));
+    if negated {
+        pi += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let start = *pattern.get(pi)?;
+        if start == ']' && !first {
+            return Some((matched != negated, pi + 1));
+        }
+        first = false;
+        if pattern.get(pi + 1) == Some(&'-')
+                && pattern.get(pi + 2).is_some_and(|e| *e != ']') {
+            let end = pattern[pi + 2];
+            if start <= c && c <= end {
+                matched = true;
+            }
+            pi += 3;
+        } else {
+            if start == c {
+                matched = true;
+            }
+            pi += 1;
+        }
+    }
+}
+
+/// Returns a boolean indicating whether the string matches the glob
+/// pattern, per fnmatch (without any flags).
+fn glob_match(string: &[char], pattern: &[char]) -> bool {
+    let mut si = 0;
+    let mut pi = 0;
+    let mut backtrack: Option<(usize, usize)> = None;
+    while si < string.len() {
+        let step = match pattern.get(pi) {
+            Some('*') => {
+                backtrack = Some((pi, si));
+                pi += 1;
+                continue;
+            }
+            Some('?') => Some(pi + 1),
+            Some('[') => match glob_match_bracket(pattern, pi + 1, string[si]) {
+                Some((true, next)) => Some(next),
+                Some((false, _)) => None,
+                /* An unterminated bracket is matched literally. */
+                _ => (string[si] == '[').then_some(pi + 1),
+            },
+            Some('\\') if pi + 1 < pattern.len() => {
+                (pattern[pi + 1] == string[si]).then_some(pi + 2)
+            }
+            Some(c) => (*c == string[si]).then_some(pi + 1),
+            None => None,
+        };
+        match step {
+            Some(next) => {
+                pi = next;
+                si += 1;
+            }
+            _ => match backtrack {
+                Some((star_pi, star_si)) => {
+                    pi = star_pi + 1;
+                    si = star_si + 1;
+                    backtrack = Some((star_pi, star_si + 1));
+                }
+                _ => return false,
+            },
+        }
+    }
+    pattern[pi..].iter().all(|c| *c == '*')
+}
+
 lazy_static! {
     static ref CAPTURE_NUM: Regex = Regex::new("\\{(\\d+)\\}").unwrap();
     static ref CAPTURE_WITHOUT_NUM: Regex = Regex::new("\\{\\}").unwrap();
+    static ref ANSI_ESCAPE: Regex =
+        Regex::new("\x1b(\\[[0-?]*[ -/]*[@-~]|\\][^\x07\x1b]*(\x07|\x1b\\\\)|[@-Z\\\\-_])").unwrap();
+    static ref SIZE_STRING: Regex =
+        Regex::new("^\\s*(\\d+(?:\\.\\d+)?)\\s*([kKmMgGtTpPeEzZyY]?)(i?)B?\\s*$").unwrap();
 }
 
 impl VM {
@@ -489,4 +750,904 @@ impl VM {
 
         return self.fmt(true);
     }
+
+    /// Takes a string as its argument, and puts the list of the
+    /// string's UTF-8 bytes onto the stack.
+    pub fn core_to_bytes(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("to-bytes requires one argument");
+            return 0;
+        }
+
+        let value_rr = self.stack.pop().unwrap();
+        let value_opt: Option<&str>;
+        to_str!(value_rr, value_opt);
+        match value_opt {
+            Some(s) => {
+                let lst = s
+                    .as_bytes()
+                    .iter()
+                    .map(|b| Value::Byte(*b))
+                    .collect::<VecDeque<Value>>();
+                self.stack.push(Value::List(Rc::new(RefCell::new(lst))));
+                1
+            }
+            _ => {
+                self.print_error("to-bytes argument must be string");
+                0
+            }
+        }
+    }
+
+    /// Takes a string or a byte list as its single argument, and
+    /// puts its base64 encoding (using the given engine) onto the
+    /// stack.
+    fn base64_encode_inner(&mut self, name: &str, engine: &GeneralPurpose) -> i32 {
+        if self.stack.is_empty() {
+            let err_str = format!("{} requires one argument", name);
+            self.print_error(&err_str);
+            return 0;
+        }
+
+        let value_rr = self.stack.pop().unwrap();
+        if let Value::List(_) = value_rr {
+            let bytes = match self.byte_list_to_vec(
+                name, &format!("{} argument", name), &value_rr
+            ) {
+                Some(bytes) => bytes,
+                _ => return 0,
+            };
+            self.stack.push(new_string_value(engine.encode(bytes)));
+            return 1;
+        }
+        let value_opt: Option<&str>;
+        to_str!(value_rr, value_opt);
+        match value_opt {
+            Some(s) => {
+                self.stack.push(new_string_value(engine.encode(s)));
+                1
+            }
+            _ => {
+                let err_str = format!("{} argument must be string or byte list", name);
+                self.print_error(&err_str);
+                0
+            }
+        }
+    }
+
+    /// Takes a base64 string as its single argument, and puts the
+    /// list of decoded bytes onto the stack.  If url_safe is None,
+    /// then the alphabet is determined from the string.
+    fn base64_decode_inner(&mut self, name: &str, url_safe: Option<bool>) -> i32 {
+        if self.stack.is_empty() {
+            let err_str = format!("{} requires one argument", name);
+            self.print_error(&err_str);
+            return 0;
+        }
+
+        let value_rr = self.stack.pop().unwrap();
+        let value_opt: Option<&str>;
+        to_str!(value_rr, value_opt);
+        match value_opt {
+            Some(s) => {
+                let s: String = s.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+                let url_safe = url_safe.unwrap_or_else(|| s.contains(['-', '_']));
+                match base64_decoder(url_safe).decode(s) {
+                    Ok(bytes) => {
+                        let lst = bytes
+                            .into_iter()
+                            .map(Value::Byte)
+                            .collect::<VecDeque<Value>>();
+                        self.stack.push(Value::List(Rc::new(RefCell::new(lst))));
+                        1
+                    }
+                    _ => {
+                        let err_str = format!("{} argument is not valid base64", name);
+                        self.print_error(&err_str);
+                        0
+                    }
+                }
+            }
+            _ => {
+                let err_str = format!("{} argument must be string", name);
+                self.print_error(&err_str);
+                0
+            }
+        }
+    }
+
+    /// Takes a string or a byte list as its single argument, and
+    /// puts its base64 encoding onto the stack.
+    pub fn core_to_base64(&mut self) -> i32 {
+        self.base64_encode_inner("to-base64", &base64::engine::general_purpose::STANDARD)
+    }
+
+    /// Takes a base64 string as its single argument, and puts the
+    /// list of decoded bytes onto the stack.  Both the standard and
+    /// the URL-safe alphabets are supported.
+    pub fn core_from_base64(&mut self) -> i32 {
+        self.base64_decode_inner("from-base64", None)
+    }
+
+    /// Takes a string or a byte list as its single argument, and
+    /// puts its unpadded URL-safe base64 encoding onto the stack.
+    pub fn core_to_base64url(&mut self) -> i32 {
+        self.base64_encode_inner(
+            "to-base64url",
+            &base64::engine::general_purpose::URL_SAFE_NO_PAD
+        )
+    }
+
+    /// Takes a URL-safe base64 string as its single argument, and
+    /// puts the list of decoded bytes onto the stack.
+    pub fn core_from_base64url(&mut self) -> i32 {
+        self.base64_decode_inner("from-base64url", Some(true))
+    }
+
+    /// Takes a byte list and a boolean as its arguments, and puts
+    /// the string represented by the bytes onto the stack.  If the
+    /// boolean is true, then invalid UTF-8 sequences are replaced
+    /// with the replacement character, and otherwise they cause an
+    /// error to be raised.
+    pub fn core_from_bytes(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("from-bytes requires two arguments");
+            return 0;
+        }
+
+        let lossy_rr = self.stack.pop().unwrap();
+        let lossy = lossy_rr.to_bool();
+        let lst_rr = self.stack.pop().unwrap();
+        let bytes = match self.byte_list_to_vec(
+            "from-bytes", "from-bytes first argument", &lst_rr
+        ) {
+            Some(bytes) => bytes,
+            _ => return 0,
+        };
+
+        if lossy {
+            let s = String::from_utf8_lossy(&bytes).to_string();
+            self.stack.push(new_string_value(s));
+            return 1;
+        }
+        match String::from_utf8(bytes) {
+            Ok(s) => {
+                self.stack.push(new_string_value(s));
+                1
+            }
+            Err(e) => {
+                let err_str = format!(
+                    "from-bytes argument is not valid UTF-8 (invalid byte at offset {})",
+                    e.utf8_error().valid_up_to()
+                );
+                self.print_error(&err_str);
+                0
+            }
+        }
+    }
+
+    /// Converts a byte list value into a vector of bytes.  The name
+    /// and argument description are used in error messages.
+    fn byte_list_to_vec(&mut self, name: &str, arg_desc: &str,
+                        lst_rr: &Value) -> Option<Vec<u8>> {
+        let mut bytes = Vec::new();
+        match lst_rr {
+            Value::List(lst) => {
+                for e in lst.borrow().iter() {
+                    match e {
+                        Value::Byte(b) => {
+                            bytes.push(*b);
+                        }
+                        _ => {
+                            let err_str = format!("{} list must contain bytes", name);
+                            self.print_error(&err_str);
+                            return None;
+                        }
+                    }
+                }
+                Some(bytes)
+            }
+            _ => {
+                let err_str = format!("{} must be byte list", arg_desc);
+                self.print_error(&err_str);
+                None
+            }
+        }
+    }
+
+    /// Takes a string as its single argument.  Puts a boolean onto
+    /// the stack indicating whether all of the string's characters
+    /// are ASCII characters.
+    pub fn core_is_ascii(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("ascii? requires one argument");
+            return 0;
+        }
+        let value_rr = self.stack.pop().unwrap();
+        let value_opt: Option<&str>;
+        to_str!(value_rr, value_opt);
+        match value_opt {
+            Some(s) => {
+                self.stack.push(Value::Bool(s.is_ascii()));
+                1
+            }
+            _ => {
+                self.print_error("ascii? argument must be string");
+                0
+            }
+        }
+    }
+
+    /// Takes a byte list as its single argument.  Puts a boolean onto
+    /// the stack indicating whether the bytes are valid UTF-8.
+    pub fn core_is_valid_utf8(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("valid-utf8? requires one argument");
+            return 0;
+        }
+        let lst_rr = self.stack.pop().unwrap();
+        match self.byte_list_to_vec("valid-utf8?", "valid-utf8? argument", &lst_rr) {
+            Some(bytes) => {
+                self.stack.push(Value::Bool(str::from_utf8(&bytes).is_ok()));
+                1
+            }
+            _ => 0,
+        }
+    }
+
+    /// Takes a string and a list of prefix strings as its arguments.
+    /// Puts a boolean onto the stack indicating whether the string
+    /// starts with any of the prefixes.
+    pub fn core_starts_with_any(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("starts-with-any requires two arguments");
+            return 0;
+        }
+
+        let prefixes_rr = self.stack.pop().unwrap();
+        let str_rr = self.stack.pop().unwrap();
+        let str_opt: Option<&str>;
+        to_str!(str_rr, str_opt);
+        if str_opt.is_none() {
+            self.print_error("first starts-with-any argument must be string");
+            return 0;
+        }
+        let s = str_opt.unwrap();
+
+        match prefixes_rr {
+            Value::List(lst) => {
+                for prefix_rr in lst.borrow().iter() {
+                    let prefix_opt: Option<&str>;
+                    to_str!(prefix_rr, prefix_opt);
+                    match prefix_opt {
+                        Some(prefix) => {
+                            if s.starts_with(prefix) {
+                                self.stack.push(Value::Bool(true));
+                                return 1;
+                            }
+                        }
+                        _ => {
+                            self.print_error("starts-with-any prefix must be string");
+                            return 0;
+                        }
+                    }
+                }
+                self.stack.push(Value::Bool(false));
+                1
+            }
+            _ => {
+                self.print_error("second starts-with-any argument must be list");
+                0
+            }
+        }
+    }
+
+    /// Takes a byte count, a unit base (1000 or 1024), and a
+    /// precision as its arguments.  Puts a human-readable string for
+    /// the byte count (e.g. "1.5G") onto the stack.
+    pub fn core_human_size(&mut self) -> i32 {
+        if self.stack.len() < 3 {
+            self.print_error("human-size requires three arguments");
+            return 0;
+        }
+
+        let precision_rr = self.stack.pop().unwrap();
+        let base_rr = self.stack.pop().unwrap();
+        let size_rr = self.stack.pop().unwrap();
+
+        let precision = match precision_rr.to_int() {
+            Some(n) if n >= 0 => n as usize,
+            _ => {
+                self.print_error("third human-size argument must be non-negative integer");
+                return 0;
+            }
+        };
+        let base = match base_rr.to_int() {
+            Some(n) if n == 1000 || n == 1024 => n as f64,
+            _ => {
+                self.print_error("second human-size argument must be 1000 or 1024");
+                return 0;
+            }
+        };
+        let size = match size_rr.to_float() {
+            Some(n) => n,
+            _ => {
+                self.print_error("first human-size argument must be number");
+                return 0;
+            }
+        };
+
+        let mut value = size.abs();
+        let mut index = 0;
+        let scale = 10f64.powi(precision as i32);
+        while index < SIZE_UNITS.len() - 1
+                && (value * scale).round() / scale >= base {
+            value /= base;
+            index += 1;
+        }
+        let sign = if size < 0.0 { "-" } else { "" };
+        let s = if index == 0 {
+            if value.fract() == 0.0 {
+                format!("{}{}", sign, value)
+            } else {
+                format!("{}{:.*}", sign, precision, value)
+            }
+        } else {
+            format!("{}{:.*}{}", sign, precision, value, SIZE_UNITS[index])
+        };
+        self.stack.push(new_string_value(s));
+        1
+    }
+
+    /// Takes a human-readable size string (e.g. "1.5G") and a unit
+    /// base (1000 or 1024) as its arguments.  Puts the corresponding
+    /// byte count onto the stack.  Units with an "i" suffix (e.g.
+    /// "Mi" or "MiB") are always treated as binary units.
+    pub fn core_parse_size(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("parse-size requires two arguments");
+            return 0;
+        }
+
+        let base_rr = self.stack.pop().unwrap();
+        let size_rr = self.stack.pop().unwrap();
+
+        let base = match base_rr.to_int() {
+            Some(n) if n == 1000 || n == 1024 => n,
+            _ => {
+                self.print_error("second parse-size argument must be 1000 or 1024");
+                return 0;
+            }
+        };
+        let size_opt: Option<&str>;
+        to_str!(size_rr, size_opt);
+        let size_str = match size_opt {
+            Some(s) => s,
+            _ => {
+                self.print_error("first parse-size argument must be string");
+                return 0;
+            }
+        };
+
+        let captures = match SIZE_STRING.captures(size_str) {
+            Some(captures) => captures,
+            _ => {
+                self.print_error("first parse-size argument is not a valid size");
+                return 0;
+            }
+        };
+        let number = captures.get(1).unwrap().as_str();
+        let unit = captures.get(2).unwrap().as_str().to_uppercase();
+        let base = if captures.get(3).unwrap().as_str().is_empty() {
+            base
+        } else {
+            1024
+        };
+        let index = SIZE_UNITS.iter().position(|u| *u == unit).unwrap();
+        let multiplier = BigInt::from_i32(base).unwrap().pow(index as u32);
+
+        let result = if number.contains('.') {
+            let n = number.parse::<f64>().unwrap();
+            let product = n * multiplier.to_f64().unwrap();
+            match BigInt::from_f64(product.round()) {
+                Some(result) => result,
+                _ => {
+                    self.print_error("first parse-size argument is too large");
+                    return 0;
+                }
+            }
+        } else {
+            number.parse::<BigInt>().unwrap() * multiplier
+        };
+        match result.to_i32() {
+            Some(n) => self.stack.push(Value::Int(n)),
+            _ => self.stack.push(Value::BigInt(result)),
+        }
+        1
+    }
+
+    /// Takes a string as its single argument.  Puts the case-folded
+    /// form of the string onto the stack, for use in case-insensitive
+    /// comparisons.
+    pub fn core_casefold(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("casefold requires one argument");
+            return 0;
+        }
+        let value_rr = self.stack.pop().unwrap();
+        let value_opt: Option<&str>;
+        to_str!(value_rr, value_opt);
+        match value_opt {
+            Some(s) => {
+                /* This uses the full case folding mappings from
+                 * CaseFolding.txt (e.g. 'ß' to "ss", and both final
+                 * and non-final sigma to 'σ'), without the
+                 * language-specific (Turkic) mappings. */
+                let folded = caseless::default_case_fold_str(s);
+                self.stack.push(new_string_value(folded));
+                1
+            }
+            _ => {
+                self.print_error("casefold argument must be string");
+                0
+            }
+        }
+    }
+
+    /// Converts a string into a slug, using the given separator and
+    /// maximum length (zero means that there is no maximum length).
+    fn slugify(s: &str, separator: &str, max_length: usize) -> String {
+        let mut slug = String::new();
+        let mut pending_separator = false;
+        for c in s.nfd().flat_map(|c| c.to_lowercase()) {
+            if is_combining_mark(c) {
+                continue;
+            }
+            let transliterated = match c {
+                'ß' => "ss",
+                'æ' => "ae",
+                'œ' => "oe",
+                'ø' => "o",
+                'đ' | 'ð' => "d",
+                'ł' => "l",
+                'þ' => "th",
+                _ => "",
+            };
+            if c.is_ascii_alphanumeric() || !transliterated.is_empty() {
+                if pending_separator && !slug.is_empty() {
+                    slug.push_str(separator);
+                }
+                pending_separator = false;
+                if transliterated.is_empty() {
+                    slug.push(c);
+                } else {
+                    slug.push_str(transliterated);
+                }
+            } else if !c.is_alphanumeric() {
+                pending_separator = true;
+            }
+        }
+        if max_length > 0 && slug.len() > max_length {
+            slug.truncate(max_length);
+            while !separator.is_empty() && slug.ends_with(separator) {
+                slug.truncate(slug.len() - separator.len());
+            }
+        }
+        slug
+    }
+
+    /// Takes a string as its single argument.  Puts a slug for the
+    /// string onto the stack: the string is lowercased, diacritics
+    /// are removed, other non-alphanumeric characters are dropped,
+    /// and runs of whitespace and punctuation are replaced with
+    /// '-'.
+    pub fn core_slugify(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("slugify requires one argument");
+            return 0;
+        }
+        let value_rr = self.stack.pop().unwrap();
+        let value_opt: Option<&str>;
+        to_str!(value_rr, value_opt);
+        match value_opt {
+            Some(s) => {
+                let slug = VM::slugify(s, "-", 0);
+                self.stack.push(new_string_value(slug));
+                1
+            }
+            _ => {
+                self.print_error("slugify argument must be string");
+                0
+            }
+        }
+    }
+
+    /// Takes a string, a separator, and a maximum length as its
+    /// arguments.  As per slugify, except that the given separator
+    /// is used, and the slug is truncated to the maximum length (if
+    /// it is not zero).
+    pub fn core_slugify_with(&mut self) -> i32 {
+        if self.stack.len() < 3 {
+            self.print_error("slugify-with requires three arguments");
+            return 0;
+        }
+
+        let max_length_rr = self.stack.pop().unwrap();
+        let separator_rr = self.stack.pop().unwrap();
+        let value_rr = self.stack.pop().unwrap();
+
+        let max_length = match max_length_rr.to_int() {
+            Some(n) if n >= 0 => n as usize,
+            _ => {
+                self.print_error("third slugify-with argument must be non-negative integer");
+                return 0;
+            }
+        };
+        let separator_opt: Option<&str>;
+        to_str!(separator_rr, separator_opt);
+        let separator = match separator_opt {
+            Some(s) => s,
+            _ => {
+                self.print_error("second slugify-with argument must be string");
+                return 0;
+            }
+        };
+        let value_opt: Option<&str>;
+        to_str!(value_rr, value_opt);
+        match value_opt {
+            Some(s) => {
+                let slug = VM::slugify(s, separator, max_length);
+                self.stack.push(new_string_value(slug));
+                1
+            }
+            _ => {
+                self.print_error("first slugify-with argument must be string");
+                0
+            }
+        }
+    }
+
+    /// Takes a string as its single argument.  Puts the number of
+    /// terminal columns needed to display the string onto the stack.
+    /// Wide characters count as two columns, combining marks count
+    /// as zero columns, and ANSI escape sequences are ignored.
+    pub fn core_display_width(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("display-width requires one argument");
+            return 0;
+        }
+        let value_rr = self.stack.pop().unwrap();
+        let value_opt: Option<&str>;
+        to_str!(value_rr, value_opt);
+        match value_opt {
+            Some(s) => {
+                let stripped = ANSI_ESCAPE.replace_all(s, "");
+                let width = display_width(&stripped);
+                self.stack.push(Value::Int(width as i32));
+                1
+            }
+            _ => {
+                self.print_error("display-width argument must be string");
+                0
+            }
+        }
+    }
+
+    /// Takes a string and a maximum width as its arguments.  Puts
+    /// the string truncated to that display width onto the stack,
+    /// with '…' appended if truncation was necessary.
+    pub fn core_truncate(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("truncate requires two arguments");
+            return 0;
+        }
+
+        let max_width_rr = self.stack.pop().unwrap();
+        let value_rr = self.stack.pop().unwrap();
+
+        let max_width = match max_width_rr.to_int() {
+            Some(n) if n >= 0 => n as usize,
+            _ => {
+                self.print_error("second truncate argument must be non-negative integer");
+                return 0;
+            }
+        };
+        let value_opt: Option<&str>;
+        to_str!(value_rr, value_opt);
+        match value_opt {
+            Some(s) => {
+                let truncated = truncate_to_width(s, "…", max_width);
+                self.stack.push(new_string_value(truncated));
+                1
+            }
+            _ => {
+                self.print_error("first truncate argument must be string");
+                0
+            }
+        }
+    }
+
+    /// Takes a string, an ellipsis string, and a maximum width as its
+    /// arguments.  As per truncate, except that the given ellipsis
+    /// string is used.
+    pub fn core_truncate_with(&mut self) -> i32 {
+        if self.stack.len() < 3 {
+            self.print_error("truncate-with requires three arguments");
+            return 0;
+        }
+
+        let max_width_rr = self.stack.pop().unwrap();
+        let ellipsis_rr = self.stack.pop().unwrap();
+        let value_rr = self.stack.pop().unwrap();
+
+        let max_width = match max_width_rr.to_int() {
+            Some(n) if n >= 0 => n as usize,
+            _ => {
+                self.print_error("third truncate-with argument must be non-negative integer");
+                return 0;
+            }
+        };
+        let ellipsis_opt: Option<&str>;
+        to_str!(ellipsis_rr, ellipsis_opt);
+        let ellipsis = match ellipsis_opt {
+            Some(s) => s,
+            _ => {
+                self.print_error("second truncate-with argument must be string");
+                return 0;
+            }
+        };
+        let value_opt: Option<&str>;
+        to_str!(value_rr, value_opt);
+        match value_opt {
+            Some(s) => {
+                let truncated = truncate_to_width(s, ellipsis, max_width);
+                self.stack.push(new_string_value(truncated));
+                1
+            }
+            _ => {
+                self.print_error("first truncate-with argument must be string");
+                0
+            }
+        }
+    }
+
+    /// Takes a string as its single argument.  Puts the string onto
+    /// the stack with each CRLF line ending converted to LF.
+    pub fn core_to_unix_newlines(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("to-unix-newlines requires one argument");
+            return 0;
+        }
+        let value_rr = self.stack.pop().unwrap();
+        let value_opt: Option<&str>;
+        to_str!(value_rr, value_opt);
+        match value_opt {
+            Some(s) => {
+                self.stack.push(new_string_value(s.replace("\r\n", "\n")));
+                1
+            }
+            _ => {
+                self.print_error("to-unix-newlines argument must be string");
+                0
+            }
+        }
+    }
+
+    /// Takes a string as its single argument.  Puts the string onto
+    /// the stack with each LF line ending converted to CRLF.  Line
+    /// endings that are already CRLF are left unchanged.
+    pub fn core_to_dos_newlines(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("to-dos-newlines requires one argument");
+            return 0;
+        }
+        let value_rr = self.stack.pop().unwrap();
+        let value_opt: Option<&str>;
+        to_str!(value_rr, value_opt);
+        match value_opt {
+            Some(s) => {
+                let mut result = String::with_capacity(s.len());
+                let mut prev = None;
+                for c in s.chars() {
+                    if c == '\n' && prev != Some('\r') {
+                        result.push('\r');
+                    }
+                    result.push(c);
+                    prev = Some(c);
+                }
+                self.stack.push(new_string_value(result));
+                1
+            }
+            _ => {
+                self.print_error("to-dos-newlines argument must be string");
+                0
+            }
+        }
+    }
+
+    /// Takes a string and a glob pattern as its arguments, and puts
+    /// a boolean onto the stack indicating whether the string
+    /// matches the pattern.  If ci is true, then the match is
+    /// case-insensitive.
+    fn glob_match_inner(&mut self, name: &str, ci: bool) -> i32 {
+        if self.stack.len() < 2 {
+            let err_str = format!("{} requires two arguments", name);
+            self.print_error(&err_str);
+            return 0;
+        }
+
+        let pattern_rr = self.stack.pop().unwrap();
+        let str_rr = self.stack.pop().unwrap();
+        let pattern_opt: Option<&str>;
+        to_str!(pattern_rr, pattern_opt);
+        let str_opt: Option<&str>;
+        to_str!(str_rr, str_opt);
+
+        match (str_opt, pattern_opt) {
+            (Some(s), Some(pattern)) => {
+                let (s, pattern) = if ci {
+                    (s.to_lowercase(), pattern.to_lowercase())
+                } else {
+                    (s.to_string(), pattern.to_string())
+                };
+                let s_chars: Vec<char> = s.chars().collect();
+                let pattern_chars: Vec<char> = pattern.chars().collect();
+                let matched = glob_match(&s_chars, &pattern_chars);
+                self.stack.push(Value::Bool(matched));
+                1
+            }
+            (Some(_), _) => {
+                let err_str = format!("second {} argument must be string", name);
+                self.print_error(&err_str);
+                0
+            }
+            _ => {
+                let err_str = format!("first {} argument must be string", name);
+                self.print_error(&err_str);
+                0
+            }
+        }
+    }
+
+    /// Takes a string and a glob pattern as its arguments, and puts
+    /// a boolean onto the stack indicating whether the string
+    /// matches the pattern.
+    pub fn core_glob_match(&mut self) -> i32 {
+        self.glob_match_inner("glob-match?", false)
+    }
+
+    /// As per core_glob_match, except that the match is
+    /// case-insensitive.
+    pub fn core_glob_match_ci(&mut self) -> i32 {
+        self.glob_match_inner("glob-match-ci?", true)
+    }
+
+    /// Takes a string and an encoding mode ('path', 'query', or
+    /// 'full') as its arguments, and puts the percent-encoded
+    /// string onto the stack.
+    pub fn core_uri_encode(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("uri-encode requires two arguments");
+            return 0;
+        }
+
+        let mode_rr = self.stack.pop().unwrap();
+        let str_rr = self.stack.pop().unwrap();
+        let mode_opt: Option<&str>;
+        to_str!(mode_rr, mode_opt);
+        let str_opt: Option<&str>;
+        to_str!(str_rr, str_opt);
+
+        let extra_opt = mode_opt.and_then(uri_encode_extra_chars);
+        match (str_opt, extra_opt) {
+            (Some(s), Some(extra)) => {
+                self.stack.push(new_string_value(uri_encode(s, extra)));
+                1
+            }
+            (Some(_), _) => {
+                self.print_error(
+                    "second uri-encode argument must be 'path', 'query', or 'full'"
+                );
+                0
+            }
+            _ => {
+                self.print_error("first uri-encode argument must be string");
+                0
+            }
+        }
+    }
+
+    /// Takes a percent-encoded string as its single argument, and
+    /// puts the decoded string onto the stack.
+    pub fn core_uri_decode(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("uri-decode requires one argument");
+            return 0;
+        }
+
+        let str_rr = self.stack.pop().unwrap();
+        let str_opt: Option<&str>;
+        to_str!(str_rr, str_opt);
+
+        match str_opt {
+            Some(s) => match uri_decode(s) {
+                Some(decoded) => {
+                    self.stack.push(new_string_value(decoded));
+                    1
+                }
+                _ => {
+                    self.print_error("uri-decode argument is not validly encoded");
+                    0
+                }
+            },
+            _ => {
+                self.print_error("uri-decode argument must be string");
+                0
+            }
+        }
+    }
+
+    /// Takes an integer as its single argument, and puts its ordinal
+    /// form (e.g. "1st", "2nd", "11th") onto the stack.
+    pub fn core_ordinal(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("ordinal requires one argument");
+            return 0;
+        }
+
+        let value_rr = self.stack.pop().unwrap();
+        let n_opt = match value_rr {
+            Value::Float(_) => None,
+            _ => value_rr.to_bigint(),
+        };
+        match n_opt {
+            Some(n) => {
+                let last_two = (n.magnitude() % 100u32).to_u32().unwrap();
+                let suffix = match (last_two, last_two % 10) {
+                    (11..=13, _) => "th",
+                    (_, 1) => "st",
+                    (_, 2) => "nd",
+                    (_, 3) => "rd",
+                    _ => "th",
+                };
+                self.stack.push(new_string_value(format!("{}{}", n, suffix)));
+                1
+            }
+            _ => {
+                self.print_error("ordinal argument must be integer");
+                0
+            }
+        }
+    }
+
+    /// Takes an integer as its single argument, and puts its English
+    /// word form (e.g. "forty-two") onto the stack.
+    pub fn core_number_words(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("number-words requires one argument");
+            return 0;
+        }
+
+        let value_rr = self.stack.pop().unwrap();
+        let n_opt = match value_rr {
+            Value::Float(_) => None,
+            _ => value_rr.to_bigint(),
+        };
+        match n_opt {
+            Some(n) => match n.to_i128() {
+                Some(n) => {
+                    self.stack.push(new_string_value(number_to_words(n)));
+                    1
+                }
+                _ => {
+                    self.print_error("number-words argument is out of range");
+                    0
+                }
+            },
+            _ => {
+                self.print_error("number-words argument must be integer");
+                0
+            }
+        }
+    }
 }
@@ -1,5 +1,5 @@
 use nix::sys::signal::Signal;
-use nix::unistd::{Group, Pid, User};
+use nix::unistd::{Gid, Group, Pid, Uid, User};
 use std::cell::RefCell;
 use std::collections::HashSet;
 use std::collections::VecDeque;
@@ -11,18 +11,28 @@ use std::os::unix::fs::MetadataExt;
 use std::os::unix::fs::PermissionsExt;
 use std::path::Path;
 use std::rc::Rc;
+use std::str::FromStr;
 use std::time::SystemTime;
 
 use chrono::{DateTime, NaiveDateTime, Utc};
 use indexmap::IndexMap;
-use num::FromPrimitive;
+use num::{FromPrimitive, ToPrimitive};
 use num_bigint::BigInt;
-use sysinfo::CpuRefreshKind;
+use sysinfo::{CpuRefreshKind, System};
 use utime::*;
 
 use crate::chunk::Value;
 use crate::vm::*;
 
+/// Converts a user or group ID into an integer value, or a bigint
+/// value if it cannot be stored in an i32.
+fn id_to_value(id: u32) -> Value {
+    match i32::try_from(id) {
+        Ok(n) => Value::Int(n),
+        _ => Value::BigInt(BigInt::from_u32(id).unwrap()),
+    }
+}
+
 impl VM {
     /// From https://stackoverflow.com/a/65192210.
     fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> io::Result<u64> {
@@ -890,8 +900,43 @@ impl VM {
         1
     }
 
-    /// Takes a process identifier and a signal name as its arguments.
-    /// Sends the relevant signal to the process.
+    /// Takes a signal value (either a signal name, like "term" or
+    /// "SIGTERM", or a signal number) and returns the corresponding
+    /// signal.
+    fn parse_signal(&mut self, name: &str, sig_rr: &Value) -> Option<Signal> {
+        let sig_res = match sig_rr {
+            Value::Int(n) => Signal::try_from(*n).ok(),
+            _ => {
+                let sig_opt: Option<&str>;
+                to_str!(sig_rr, sig_opt);
+                match sig_opt {
+                    Some(sig) => {
+                        let sig_uc = sig.to_uppercase();
+                        if let Ok(n) = sig_uc.parse::<i32>() {
+                            Signal::try_from(n).ok()
+                        } else if sig_uc.starts_with("SIG") {
+                            Signal::from_str(&sig_uc).ok()
+                        } else {
+                            Signal::from_str(&format!("SIG{}", sig_uc)).ok()
+                        }
+                    }
+                    _ => {
+                        let err_str = format!("second {} argument must be signal", name);
+                        self.print_error(&err_str);
+                        return None;
+                    }
+                }
+            }
+        };
+        if sig_res.is_none() {
+            self.print_error("invalid signal");
+        }
+        sig_res
+    }
+
+    /// Takes a process ID and a signal as its arguments, and sends
+    /// the signal to the process (or, if the process ID is negative,
+    /// to the corresponding process group).
     pub fn core_kill(&mut self) -> i32 {
         if self.stack.len() < 2 {
             self.print_error("kill requires two arguments");
@@ -899,46 +944,58 @@ impl VM {
         }
 
         let sig_rr = self.stack.pop().unwrap();
-        let sig_opt: Option<&str>;
-        to_str!(sig_rr, sig_opt);
-
         let pid_rr = self.stack.pop().unwrap();
-        let pid_int_opt = pid_rr.to_int();
-
-        match (pid_int_opt, sig_opt) {
-            (Some(pid), Some(sig)) => {
-                let sig_lc = sig.to_lowercase();
-                let sig_obj = match &sig_lc[..] {
-                    "hup" => Signal::SIGHUP,
-                    "int" => Signal::SIGINT,
-                    "term" => Signal::SIGTERM,
-                    "kill" => Signal::SIGKILL,
-                    "usr1" => Signal::SIGUSR1,
-                    "usr2" => Signal::SIGUSR2,
-                    "cont" => Signal::SIGCONT,
-                    "stop" => Signal::SIGSTOP,
-                    _ => {
-                        self.print_error("invalid signal");
-                        return 0;
-                    }
-                };
-                let res = nix::sys::signal::kill(Pid::from_raw(pid), sig_obj);
-                match res {
-                    Ok(_) => {}
-                    Err(e) => {
-                        let err_str = format!("unable to kill process: {}", e);
-                        self.print_error(&err_str);
-                        return 0;
-                    }
-                }
-                1
+        let pid = match pid_rr.to_int() {
+            Some(pid) => pid,
+            _ => {
+                self.print_error("first kill argument must be process");
+                return 0;
             }
-            (Some(_), _) => {
-                self.print_error("second kill argument must be signal");
+        };
+        let sig_obj = match self.parse_signal("kill", &sig_rr) {
+            Some(sig_obj) => sig_obj,
+            _ => return 0,
+        };
+
+        let res = nix::sys::signal::kill(Pid::from_raw(pid), sig_obj);
+        match res {
+            Ok(_) => 1,
+            Err(e) => {
+                let err_str = format!("unable to kill process {}: {}", pid, e);
+                self.print_error(&err_str);
                 0
             }
-            (_, _) => {
-                self.print_error("first kill argument must be process");
+        }
+    }
+
+    /// Takes a process group ID and a signal as its arguments, and
+    /// sends the signal to each process in the group.
+    pub fn core_killpg(&mut self) -> i32 {
+        if self.stack.len() < 2 {
+            self.print_error("killpg requires two arguments");
+            return 0;
+        }
+
+        let sig_rr = self.stack.pop().unwrap();
+        let pgid_rr = self.stack.pop().unwrap();
+        let pgid = match pgid_rr.to_int() {
+            Some(pgid) if pgid > 0 => pgid,
+            _ => {
+                self.print_error("first killpg argument must be process group");
+                return 0;
+            }
+        };
+        let sig_obj = match self.parse_signal("killpg", &sig_rr) {
+            Some(sig_obj) => sig_obj,
+            _ => return 0,
+        };
+
+        let res = nix::sys::signal::killpg(Pid::from_raw(pgid), sig_obj);
+        match res {
+            Ok(_) => 1,
+            Err(e) => {
+                let err_str = format!("unable to kill process group {}: {}", pgid, e);
+                self.print_error(&err_str);
                 0
             }
         }
@@ -1064,6 +1121,130 @@ impl VM {
         }
     }
 
+    /// Pops a user or group ID from the stack, and returns it as a
+    /// u32.
+    fn pop_id(&mut self, name: &str) -> Option<u32> {
+        if self.stack.is_empty() {
+            let err_str = format!("{} requires one argument", name);
+            self.print_error(&err_str);
+            return None;
+        }
+
+        let id_rr = self.stack.pop().unwrap();
+        let id_opt = match id_rr {
+            Value::Int(_) | Value::BigInt(_) => {
+                id_rr.to_bigint().and_then(|n| n.to_u32())
+            }
+            _ => None,
+        };
+        if id_opt.is_none() {
+            let err_str = format!("{} argument must be non-negative integer", name);
+            self.print_error(&err_str);
+        }
+        id_opt
+    }
+
+    /// Pops a user or group name from the stack.
+    fn pop_id_name(&mut self, name: &str) -> Option<String> {
+        if self.stack.is_empty() {
+            let err_str = format!("{} requires one argument", name);
+            self.print_error(&err_str);
+            return None;
+        }
+
+        let name_rr = self.stack.pop().unwrap();
+        let name_opt: Option<&str>;
+        to_str!(name_rr, name_opt);
+        match name_opt {
+            Some(s) => Some(s.to_string()),
+            _ => {
+                let err_str = format!("{} argument must be string", name);
+                self.print_error(&err_str);
+                None
+            }
+        }
+    }
+
+    /// Takes a user ID as its single argument, and returns the name
+    /// of the corresponding user, or null if there is no such user.
+    pub fn core_uid_name(&mut self) -> i32 {
+        let uid = match self.pop_id("uid-name") {
+            Some(uid) => uid,
+            _ => return 0,
+        };
+        match User::from_uid(Uid::from_raw(uid)) {
+            Ok(Some(user)) => self.stack.push(new_string_value(user.name)),
+            Ok(None) => self.stack.push(Value::Null),
+            Err(e) => {
+                let err_str = format!("unable to look up user: {}", e);
+                self.print_error(&err_str);
+                return 0;
+            }
+        }
+        1
+    }
+
+    /// Takes a group ID as its single argument, and returns the name
+    /// of the corresponding group, or null if there is no such group.
+    pub fn core_gid_name(&mut self) -> i32 {
+        let gid = match self.pop_id("gid-name") {
+            Some(gid) => gid,
+            _ => return 0,
+        };
+        match Group::from_gid(Gid::from_raw(gid)) {
+            Ok(Some(group)) => self.stack.push(new_string_value(group.name)),
+            Ok(None) => self.stack.push(Value::Null),
+            Err(e) => {
+                let err_str = format!("unable to look up group: {}", e);
+                self.print_error(&err_str);
+                return 0;
+            }
+        }
+        1
+    }
+
+    /// Takes a user name as its single argument, and returns the ID
+    /// of the corresponding user, or null if there is no such user.
+    pub fn core_name_uid(&mut self) -> i32 {
+        let name = match self.pop_id_name("name-uid") {
+            Some(name) => name,
+            _ => return 0,
+        };
+        match User::from_name(&name) {
+            Ok(Some(user)) => {
+                self.stack.push(id_to_value(user.uid.as_raw()))
+            }
+            Ok(None) => self.stack.push(Value::Null),
+            Err(e) => {
+                let err_str = format!("unable to look up user: {}", e);
+                self.print_error(&err_str);
+                return 0;
+            }
+        }
+        1
+    }
+
+    /// Takes a group name as its single argument, and returns the ID
+    /// of the corresponding group, or null if there is no such group.
+    pub fn core_name_gid(&mut self) -> i32 {
+        let name = match self.pop_id_name("name-gid") {
+            Some(name) => name,
+            _ => return 0,
+        };
+        match Group::from_name(&name) {
+            Ok(Some(group)) => {
+                self.stack.push(id_to_value(group.gid.as_raw()))
+            }
+            Ok(None) => self.stack.push(Value::Null),
+            Err(e) => {
+                let err_str = format!("unable to look up group: {}", e);
+                self.print_error(&err_str);
+                return 0;
+            }
+        }
+        1
+    }
+
     /// Takes a path as its single argument, and attempts to make a
     /// directory at that path.
     pub fn core_mkdir(&mut self) -> i32 {
@@ -1184,4 +1365,115 @@ impl VM {
         }
         1
     }
+
+    /// Takes a stream name (one of "stdin", "stdout", or "stderr")
+    /// as its single argument, and returns a boolean indicating
+    /// whether that stream is a terminal.
+    pub fn core_is_tty(&mut self) -> i32 {
+        if self.stack.is_empty() {
+            self.print_error("is-tty requires one argument");
+            return 0;
+        }
+
+        let value_rr = self.stack.pop().unwrap();
+        let value_opt: Option<&str>;
+        to_str!(value_rr, value_opt);
+
+        let stream = match value_opt {
+            Some("stdin") => atty::Stream::Stdin,
+            Some("stdout") => atty::Stream::Stdout,
+            Some("stderr") => atty::Stream::Stderr,
+            _ => {
+                self.print_error("is-tty argument must be 'stdin', 'stdout', or 'stderr'");
+                return 0;
+            }
+        };
+        self.stack.push(Value::Bool(atty::is(stream)));
+        1
+    }
+
+    /// Returns a hash containing the number of rows and columns in
+    /// the terminal.  If the terminal size cannot be determined
+    /// (e.g. because standard output is not a terminal), then null
+    /// is returned instead.
+    pub fn core_term_size(&mut self) -> i32 {
+        match term_size::dimensions() {
+            Some((cols, rows)) => {
+                let mut map = IndexMap::new();
+                map.insert("rows".to_string(), Value::Int(rows as i32));
+                map.insert("cols".to_string(), Value::Int(cols as i32));
+                self.stack.push(Value::Hash(Rc::new(RefCell::new(map))));
+            }
+            _ => {
+                self.stack.push(Value::Null);
+            }
+        }
+        1
+    }
+
+    /// Returns the current hostname of the system.
+    pub fn core_hostname(&mut self) -> i32 {
+        match System::host_name() {
+            Some(hostname) => {
+                self.stack.push(new_string_value(hostname));
+                1
+            }
+            _ => {
+                self.print_error("unable to get hostname");
+                0
+            }
+        }
+    }
+
+    /// Returns a hash containing the operating system name, the
+    /// operating system version, the kernel version, and the CPU
+    /// architecture.  Elements that cannot be determined are set to
+    /// null.
+    pub fn core_uname(&mut self) -> i32 {
+        let to_value = |s: Option<String>| {
+            s.map(new_string_value).unwrap_or(Value::Null)
+        };
+        let mut map = IndexMap::new();
+        map.insert("os".to_string(), to_value(System::name()));
+        map.insert("os-version".to_string(), to_value(System::os_version()));
+        map.insert("kernel-version".to_string(),
+                   to_value(System::kernel_version()));
+        map.insert("arch".to_string(), to_value(System::cpu_arch()));
+        self.stack.push(Value::Hash(Rc::new(RefCell::new(map))));
+        1
+    }
+
+    /// Returns a list containing the one-, five-, and fifteen-minute
+    /// load averages for the system.
+    pub fn core_loadavg(&mut self) -> i32 {
+        let load_avg = System::load_average();
+        let mut lst = VecDeque::new();
+        lst.push_back(Value::Float(load_avg.one));
+        lst.push_back(Value::Float(load_avg.five));
+        lst.push_back(Value::Float(load_avg.fifteen));
+        self.stack.push(Value::List(Rc::new(RefCell::new(lst))));
+        1
+    }
+
+    /// Returns a hash containing the total, used, free, and available
+    /// memory for the system, as well as the total, used, and free
+    /// swap space, in bytes.
+    pub fn core_meminfo(&mut self) -> i32 {
+        self.instantiate_sys();
+        let sysopt = &mut self.sys;
+        let sys = &mut sysopt.as_mut().unwrap();
+        sys.refresh_memory();
+
+        let to_value = |n: u64| Value::BigInt(BigInt::from_u64(n).unwrap());
+        let mut map = IndexMap::new();
+        map.insert("total".to_string(), to_value(sys.total_memory()));
+        map.insert("used".to_string(), to_value(sys.used_memory()));
+        map.insert("free".to_string(), to_value(sys.free_memory()));
+        map.insert("available".to_string(), to_value(sys.available_memory()));
+        map.insert("swap-total".to_string(), to_value(sys.total_swap()));
+        map.insert("swap-used".to_string(), to_value(sys.used_swap()));
+        map.insert("swap-free".to_string(), to_value(sys.free_swap()));
+        self.stack.push(Value::Hash(Rc::new(RefCell::new(map))));
+        1
+    }
 }
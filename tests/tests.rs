@@ -485,6 +485,399 @@ fn grep_test_generator() {
     );
 }
 
+#[test]
+fn reject_test() {
+    basic_test("(1 2 3) [2 =] reject", "v[gen (\n    0: 1\n    1: 3\n)]");
+    basic_test(
+        "10 range; [5 <] reject; take-all",
+        "(\n    0: 5\n    1: 6\n    2: 7\n    3: 8\n    4: 9\n)",
+    );
+    basic_test("(asdf qwer) [as m] reject; r;", "(\n    0: qwer\n)");
+}
+
+#[test]
+fn split_at_test() {
+    basic_test(
+        "(1 2 3 4) 1 split-at;",
+        "(\n    0: 1\n)\n(\n    0: 2\n    1: 3\n    2: 4\n)",
+    );
+    basic_test("(1 2) 5 split-at;", "(\n    0: 1\n    1: 2\n)\n()");
+    basic_test("(1 2) 0 split-at;", "()\n(\n    0: 1\n    1: 2\n)");
+    basic_test(
+        "5 range; 3 split-at; take-all;",
+        "(\n    0: 0\n    1: 1\n    2: 2\n)\n(\n    0: 3\n    1: 4\n)",
+    );
+    basic_error_test("(1 2) -1 split-at;", "1:11: second split-at argument must be non-negative integer");
+}
+
+#[test]
+fn position_test() {
+    basic_test("(a b c b) b position;", "1");
+    basic_test("(a b c b) d position;", "-1");
+    basic_test("(1 2 3) 2.0 position;", "1");
+    basic_test("(1 2 3 4) [2 >] position;", "2");
+    basic_test("5 range; 3 position;", "3");
+    basic_test("(a b c b) b positions;", "(\n    0: 1\n    1: 3\n)");
+    basic_test("(1 2 3 4) [5 >] positions;", "()");
+}
+
+#[test]
+fn contains_test() {
+    basic_test("(1 2 3) 2 contains?;", ".t");
+    basic_test("(1 2 3) 4 contains?;", ".f");
+    basic_test("h(a 1 b 2) 2 contains?;", ".t");
+    basic_test("h(a 1 b 2) a contains?;", ".f");
+    basic_test("s(1 2 3) 2 contains?;", ".t");
+    basic_test("s(1 2 3) 4 contains?;", ".f");
+    basic_test("asdf sd contains?;", ".t");
+    basic_test("asdf x contains?;", ".f");
+}
+
+#[test]
+fn top_test() {
+    basic_test(
+        "(a b c b c d c) 2 top;",
+        "(\n    0: (\n        0: c\n        1: 3\n    )\n    1: (\n        0: b\n        1: 2\n    )\n)",
+    );
+    basic_test("(a b a) 10 top; len;", "2");
+    basic_test("h(x 1 y 5) 1 top; 0 get; 0 get;", "y");
+    basic_test("() 3 top;", "()");
+}
+
+#[test]
+fn pivot_test() {
+    basic_test(
+        "(h(id 1 name a) h(id 2 name b)) id .f pivot; 2 get; name get;",
+        "b",
+    );
+    basic_test(
+        "(h(id 1 name a) h(id 2 name b) h(id 1 name c)) id .f pivot; 1 get; [name get] map; r;",
+        "(\n    0: a\n    1: c\n)",
+    );
+    basic_test(
+        "(h(id 1 name a) h(id \"\" name b) h(name c)) id .t pivot; dup; 0 get; \"\" get; name get; swap; 1 get; [name get] map; r;",
+        "b\n(\n    0: c\n)",
+    );
+    basic_test(
+        "(h(id 1 name a)) id .t pivot; 1 get; len;",
+        "0",
+    );
+    basic_error_test(
+        "(h(id 1 name a) h(name b)) id .f pivot;",
+        "1:36: pivot record does not contain key 'id'",
+    );
+}
+
+#[test]
+fn join_on_test() {
+    let records = "(h(id 1 name a) h(id 2 name b)) (h(id 1 age 30) h(id 3 age 40) h(id 1 age 31))";
+    basic_test(
+        &format!("{} id join-on; [age get] map; r;", records),
+        "(\n    0: 30\n    1: 31\n)",
+    );
+    basic_test(
+        &format!("{} id left-join-on; [age get; is-null] map; r;", records),
+        "(\n    0: .f\n    1: .f\n    2: .t\n)",
+    );
+    basic_test(
+        &format!("{} id outer-join-on; [name get; str] map; r;", records),
+        "(\n    0: a\n    1: a\n    2: b\n    3: \"\"\n)",
+    );
+}
+
+#[test]
+fn flatten_hash_test() {
+    let json = "\"{\\\"a\\\":{\\\"b\\\":1,\\\"c\\\":[{\\\"name\\\":\\\"x\\\"},{\\\"name\\\":\\\"y\\\"}]},\\\"d\\\":{}}\" from-json;";
+    basic_test(
+        &format!("{} \"/\" flatten-hash; keys; r;", json),
+        "(\n    0: a/b\n    1: a/c/0/name\n    2: a/c/1/name\n    3: d\n)",
+    );
+    basic_test(
+        &format!("{} \".\" flatten-hash; \".\" unflatten-hash; to-json;", json),
+        "{\\\"a\\\":{\\\"b\\\":1,\\\"c\\\":[{\\\"name\\\":\\\"x\\\"},{\\\"name\\\":\\\"y\\\"}]},\\\"d\\\":{}}",
+    );
+    basic_error_test(
+        "h(a.b 1 a 2) \".\" unflatten-hash;",
+        "1:19: unflatten-hash key 'a.b' conflicts with another key",
+    );
+}
+
+#[test]
+fn select_test() {
+    let json = "\"{\\\"items\\\":[{\\\"name\\\":\\\"a\\\",\\\"id\\\":1},{\\\"name\\\":\\\"b\\\",\\\"id\\\":2}]}\" from-json;";
+    basic_test(
+        &format!("{} \".items[].name\" select;", json),
+        "(\n    0: a\n    1: b\n)",
+    );
+    basic_test(
+        &format!("{} \".items | .[] | .id\" select;", json),
+        "(\n    0: 1\n    1: 2\n)",
+    );
+    basic_test(&format!("{} \".items[-1].name\" select;", json), "(\n    0: b\n)");
+    basic_test(&format!("{} \".missing.name\" select;", json), "(\n    0: null\n)");
+    basic_test(&format!("{} \".\" select; len;", json), "1");
+    basic_error_test(
+        "h(a 1) \".a.b\" select;",
+        "1:16: select failed: cannot get field 'b' from int",
+    );
+}
+
+#[test]
+fn path_env_test() {
+    basic_test(
+        "/bin:/usr/bin path-split;",
+        "(\n    0: /bin\n    1: /usr/bin\n)",
+    );
+    basic_test("(/a /b) path-join;", "/a:/b");
+    basic_test(
+        "COSH_TEST_PATH /bin:/usr/bin setenv; COSH_TEST_PATH /usr/bin path-prepend; COSH_TEST_PATH getenv;",
+        "/usr/bin:/bin",
+    );
+    basic_test(
+        "COSH_TEST_PATH /bin setenv; COSH_TEST_PATH /opt path-append; COSH_TEST_PATH /bin path-append; COSH_TEST_PATH getenv;",
+        "/opt:/bin",
+    );
+}
+
+#[test]
+fn defer_test() {
+    basic_test(
+        ": f [a println] defer; [b println] defer; c println; ,, f;",
+        "c\nb\na",
+    );
+    basic_test(
+        ": f [cleanup println] defer; .t if; early println; return; then; late println; ,, f;",
+        "early\ncleanup",
+    );
+    basic_test(
+        "[end println] defer; : f [f-end println] defer; ,, f; start println;",
+        "f-end\nstart\nend",
+    );
+    basic_test("(1 2) [[x print] defer; 1 +] map; r;", "xx(\n    0: 2\n    1: 3\n)");
+}
+
+#[test]
+fn defer_error_test() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, ": f [cleanup println] defer; boom error; ,, f;").unwrap();
+
+    let mut cmd = Command::cargo_bin("cosh").unwrap();
+    let path = file.path();
+    let assert = cmd.arg("--no-cosh-conf").arg(path).assert();
+    assert
+        .success()
+        .stdout("cleanup\n")
+        .stderr("1:36: boom\n");
+}
+
+#[test]
+fn defer_failure_test() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, ": f [boom error] defer; [cleanup println] defer; ,, f; after println;").unwrap();
+
+    let mut cmd = Command::cargo_bin("cosh").unwrap();
+    let path = file.path();
+    let assert = cmd.arg("--no-cosh-conf").arg(path).assert();
+    assert
+        .success()
+        .stdout("cleanup\n")
+        .stderr("1:11: boom\n");
+}
+
+#[test]
+fn with_file_test() {
+    let file = NamedTempFile::new().unwrap();
+    let path = file.path().to_str().unwrap();
+    basic_test(
+        &format!("{} w [asdf writeline] with-file; {} r [readline] with-file;", path, path),
+        "asdf",
+    );
+    basic_error_test(
+        &format!("{} w [qwer writeline; boom error] with-file;", path),
+        &format!("1:{}: boom", path.len() + 26),
+    );
+    basic_test(&format!("{} r [readline] with-file;", path), "qwer");
+}
+
+#[test]
+fn with_tempdir_test() {
+    basic_test(
+        "[dup; /x ++; w open; dup; hello writeline; close] with-tempdir; dup; is-dir; swap; len; 0 >;",
+        ".f\n.t",
+    );
+    basic_test(
+        "[d var; d !; d @; /x ++; w [hello writeline] with-file; d @; /x ++; r [readline] with-file] with-tempdir;",
+        "hello",
+    );
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "[d var; d !; d @; /y ++; touch; d @; println; boom error] with-tempdir;").unwrap();
+    let mut cmd = Command::cargo_bin("cosh").unwrap();
+    let output = cmd.arg("--no-cosh-conf").arg(file.path()).output().unwrap();
+    let dir = String::from_utf8(output.stdout).unwrap();
+    assert!(!dir.trim().is_empty());
+    assert!(!std::path::Path::new(dir.trim()).exists());
+}
+
+#[test]
+fn tac_test() {
+    let mut file = NamedTempFile::new().unwrap();
+    write!(file, "a\nb\nc").unwrap();
+    let path = file.path().to_str().unwrap();
+    basic_test(
+        &format!("{} tac; take-all;", path),
+        "(\n    0: c\n    1: \"b\\n\"\n    2: \"a\\n\"\n)",
+    );
+
+    let mut file = NamedTempFile::new().unwrap();
+    for i in 1..=5000 {
+        writeln!(file, "{}", i).unwrap();
+    }
+    let path = file.path().to_str().unwrap();
+    basic_test(&format!("{} tac; 2 take;", path), "(\n    0: \"5000\\n\"\n    1: \"4999\\n\"\n)");
+    basic_test(&format!("{} tac; take-all; dup; len; swap; 4999 get;", path), "5000\n\"1\\n\"");
+    basic_test(
+        &format!("{} tac; dup; readline; swap; dup; readline; swap; reverse-file-reader assert-type; close;", path),
+        "\"5000\\n\"\n\"4999\\n\"",
+    );
+}
+
+#[test]
+fn head_tail_test() {
+    basic_test("10 range; 3 head;", "(\n    0: 0\n    1: 1\n    2: 2\n)");
+    basic_test("10 range; 3 tail;", "(\n    0: 7\n    1: 8\n    2: 9\n)");
+    basic_test("10 range; dup; 3 head; drop; shift;", "3");
+    basic_test("(1 2 3) 5 head; len;", "3");
+    basic_test("(1 2 3) 2 tail;", "(\n    0: 2\n    1: 3\n)");
+    basic_test("(1 2 3) 0 tail;", "()");
+    basic_test("README.md 1 head;", "(\n    0: \"## cosh\\n\"\n)");
+}
+
+#[test]
+fn shuf_test() {
+    basic_test("10 range; shuf; len;", "10");
+    basic_test("10 range; shuf; sort; 9 get;", "9");
+    basic_test("(1 2 3) shuf; len;", "3");
+    basic_test("100 range; 5 shuf-n; len;", "5");
+    basic_test("(1 2) 5 shuf-n; sort;", "(\n    0: 1\n    1: 2\n)");
+    basic_test("10 range; 0 shuf-n;", "()");
+    basic_test("README.md 1 shuf-n; len;", "1");
+}
+
+#[test]
+fn paste_test() {
+    basic_test(
+        "((a b c) (1 2)) \",\" paste; take-all;",
+        "(\n    0: a,1\n    1: b,2\n    2: c,\n)",
+    );
+    basic_test(
+        "((a) (1 2)) \"-\" paste; take-all;",
+        "(\n    0: a-1\n    1: -2\n)",
+    );
+    basic_test("() \",\" paste; take-all;", "()");
+
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "x\ny").unwrap();
+    let path = file.path().to_str().unwrap();
+    basic_test(
+        &format!("({} f<; (1 2)) \" \" paste; take-all;", path),
+        "(\n    0: \"x 1\"\n    1: \"y 2\"\n)",
+    );
+}
+
+#[test]
+fn comm_test() {
+    basic_test(
+        "(a b d e) (b c e f) 123 comm; [dup; 0 get; swap; 1 get; str; ++] map; take-all; \",\" join",
+        "a1,b3,c2,d1,e3,f2",
+    );
+    basic_test(
+        "(a b d e) (b c e f) 3 comm; [0 get] map; take-all;",
+        "(\n    0: b\n    1: e\n)",
+    );
+    basic_test(
+        "(1 2 3) () 12 comm; [0 get] map; take-all;",
+        "(\n    0: 1\n    1: 2\n    2: 3\n)",
+    );
+}
+
+#[test]
+fn comm_error_test() {
+    for cols in ["4", "x", "\"\"", "124"] {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "(a b) (b c) {} comm; take-all;", cols).unwrap();
+
+        let mut cmd = Command::cargo_bin("cosh").unwrap();
+        let path = file.path();
+        let output = cmd.arg("--no-cosh-conf").arg(path).output().unwrap();
+        assert!(output.status.success());
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.ends_with(": third comm argument must contain only 1, 2, or 3\n"));
+    }
+}
+
+#[test]
+fn flush_test() {
+    basic_test("abc print; flush; \"\" println;", "abc");
+    basic_test(".t autoflush; abc print; .f autoflush; def println;", "abcdef");
+}
+
+#[test]
+fn stdin_test() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "stdin; readline; print; stdin; take-all; len; println; stdin; readline; is-null;").unwrap();
+
+    let mut cmd = Command::cargo_bin("cosh").unwrap();
+    let path = file.path();
+    let assert = cmd
+        .arg("--no-cosh-conf")
+        .arg(path)
+        .write_stdin("a\nb\nc\n")
+        .assert();
+    assert.success().stdout("a\n2\n.t\n");
+}
+
+#[test]
+fn std_writer_test() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "abc print; stdout; \"out\\n\" writeline; stderr; \"err\\n\" writeline; def println;").unwrap();
+
+    let mut cmd = Command::cargo_bin("cosh").unwrap();
+    let path = file.path();
+    let assert = cmd.arg("--no-cosh-conf").arg(path).assert();
+    assert.success().stdout("abcout\ndef\n").stderr("err\n");
+}
+
+#[test]
+fn std_writer_interleaved_test() {
+    basic_test(
+        "stdout; o var; o !; abc print; o @; def writeline; ghi print; o @; jkl to-bytes; write; \"\" println;",
+        "abcdefghijkl",
+    );
+}
+
+#[test]
+fn tty_test() {
+    basic_test("stdout is-tty;", ".f");
+    basic_test("term-size; is-null;", ".t");
+    basic_error_test(
+        "asdf is-tty;",
+        "1:6: is-tty argument must be 'stdin', 'stdout', or 'stderr'",
+    );
+}
+
+#[test]
+fn progress_test() {
+    basic_test("3 10 progress; 0 0 progress; progress-done; 1", "1");
+    basic_test(
+        "3 range; 3 progress-gen; take-all;",
+        "(\n    0: 0\n    1: 1\n    2: 2\n)",
+    );
+    basic_error_test(
+        "3 -1 progress;",
+        "1:6: second progress argument must be non-negative integer",
+    );
+}
+
 #[test]
 fn map_test_generator() {
     basic_test(
@@ -576,6 +969,53 @@ fn json_test() {
         "h(\n    \"num1\": 0\n    \"num2\": 100\n    \"num3\": 123.456\n    \"num4\": -123456789123\n    \"num5\": 123456789123\n)");
 }
 
+#[test]
+fn to_json_with_test() {
+    basic_test("h(a 1 b 2 c 3) h(order (a c)) to-json-with", "{\\\"a\\\":1,\\\"c\\\":3,\\\"b\\\":2}");
+    basic_test("h(a 1 b 2 c 3) h(order (a a c)) to-json-with", "{\\\"a\\\":1,\\\"c\\\":3,\\\"b\\\":2}");
+    basic_test("h(a 1 b 2 c 3) h(include s(a b)) to-json-with", "{\\\"b\\\":2,\\\"a\\\":1}");
+    basic_test(
+        "h(a 1 b h(a 1 b 2)) h(exclude (a)) to-json-with",
+        "{\\\"b\\\":{\\\"b\\\":2,\\\"a\\\":1}}",
+    );
+    basic_test(
+        "h(a 1 b h(a 1 b 2)) h(exclude (a) recursive .t) to-json-with",
+        "{\\\"b\\\":{\\\"b\\\":2}}",
+    );
+    basic_test(
+        "(h(a 1 b 2) h(a 3 b 4)) h(order (a)) to-json-with",
+        "[{\\\"a\\\":1,\\\"b\\\":2},{\\\"a\\\":3,\\\"b\\\":4}]",
+    );
+    basic_error_test(
+        "h(a 1) h(foo 1) to-json-with",
+        "1:19: to-json-with option 'foo' is not supported",
+    );
+}
+
+#[test]
+fn json_validate_test() {
+    basic_test(
+        "'{\"id\":1,\"tags\":[\"x\"]}' from-json; h(type hash required (id) properties h(id h(type int) tags h(type list items h(type str)))) json-validate; len",
+        "0",
+    );
+    basic_test(
+        "'{\"tags\":[\"x\",2]}' from-json; h(type hash required (id) properties h(tags h(type list items h(type str)))) json-validate; 1 get",
+        "\".tags[1]: expected str, got int\"",
+    );
+    basic_test(
+        "'[1,null]' from-json; h(items h(type (number \"null\"))) json-validate; len",
+        "0",
+    );
+    basic_test(
+        "5 h(type str) json-validate; 0 get",
+        "\".: expected str, got int\"",
+    );
+    basic_error_test(
+        "5 h(typo str) json-validate",
+        "1:16: json-validate schema is invalid: schema key 'typo' is not supported",
+    );
+}
+
 #[test]
 fn json_file_test() {
     basic_test(
@@ -585,6 +1025,41 @@ fn json_file_test() {
     basic_test("test-data/json2 f<; \"\" join; from-json;", "h(\n    \"asdf\": 1\n    \"qwer\": 2\n    \"tyui\": h(\n        \"asdf\": 5\n    )\n    \"zxcv\": (\n        0: 3\n        1: 4\n    )\n)");
 }
 
+#[test]
+fn json_events_test() {
+    basic_test(
+        "test-data/json1 r open; [drop] json-events; 4 mlist; , join",
+        "start-object,key,value,end-object",
+    );
+    basic_test("'{\"a\":[1,\"b\",2.5]}' [nip] json-events; 8 mlist; 1 get", "a");
+    basic_test("'[1,\"b\",2.5]' [nip] json-events; 5 mlist; 3 get", "2.5");
+    basic_test("'\"\\u00e9\\ud83d\\ude00\"' [nip] json-events", "é😀");
+    basic_error_test(
+        "'[1,' [drop; drop] json-events",
+        "1:21: json-events input is not valid JSON: unexpected end of input",
+    );
+    basic_error_test(
+        "'{\"a\" 1}' [drop; drop] json-events",
+        "1:25: json-events input is not valid JSON: expected ':' after object key",
+    );
+    basic_error_test(
+        "'[01]' [drop; drop] json-events",
+        "1:22: json-events input is not valid JSON: invalid token '01'",
+    );
+    basic_error_test(
+        "'[1.]' [drop; drop] json-events",
+        "1:22: json-events input is not valid JSON: invalid token '1.'",
+    );
+    basic_error_test(
+        "'[1e]' [drop; drop] json-events",
+        "1:22: json-events input is not valid JSON: invalid token '1e'",
+    );
+    basic_error_test(
+        "'[-]' [drop; drop] json-events",
+        "1:21: json-events input is not valid JSON: invalid token '-'",
+    );
+}
+
 #[test]
 fn xml_test() {
     basic_test(
@@ -708,6 +1183,44 @@ fn return_test() {
     basic_test(": f ding println; return; ding println; ,, f;", "ding");
 }
 
+#[test]
+fn return_nested_test() {
+    basic_test(": f begin; 1 if; 10 return; then; 20 .f until; 30 ,, f;", "10");
+    basic_test("
+: first-big begin; dup; shift; dup; 2 >; if; nip; return; then; drop; .f until; ,,
+(1 2 3 4) first-big", "3");
+    basic_test("
+: f
+    begin;
+        begin;
+            1 if; inner return; then;
+            .f until;
+        outer
+        .f until;
+    ,,
+f;", "inner");
+    basic_test("
+x var; 100 x !;
+: f x var; 5 x !;
+    begin; x @; 1 -; x !; x @; 2 =; if; x @; return; then; .f until;
+    99 ,,
+f; x @;", "2\n100");
+    basic_test("
+: f begin-scope; y var; 5 y !; 1 if; y @; return; then; end-scope; ,,
+f; f; +;", "10");
+    basic_test(": f (1 2 3) [dup; 2 =; if; return; then; 10 *] map; ,, f; , join", "10,2,30");
+    basic_test("
+:~ g 0 0
+    drop;
+    i var; 0 i !;
+    begin;
+        i @; 3 =; if; return; then;
+        i @; yield;
+        i @; 1 +; i !;
+        .f until; ,,
+g; take-all; len", "3");
+}
+
 #[test]
 fn sort_test() {
     basic_test(
@@ -957,6 +1470,39 @@ fn ip_test() {
     );
 }
 
+#[test]
+fn ip_range_ints_test() {
+    basic_test("10.0.0.0/8 ip; ip.to-range-ints; 2 mlist; str map; , join", "167772160,184549375");
+    basic_test("0.0.0.0/0 ip; ip.to-range-ints; nip", "4294967295");
+    basic_test("::/0 ip; ip.to-range-ints; nip", "340282366920938463463374607431768211455");
+    basic_test("10.1.2.3 ip; 10.0.0.0/8 ip; ip.to-range-ints; ip.int-contains?", ".t");
+    basic_test("11.1.2.3 ip; 10.0.0.0/8 ip; ip.to-range-ints; ip.int-contains?", ".f");
+    basic_test("8.0.0.0/6 ip; 10.0.0.0/8 ip; ip.to-range-ints; ip.int-contains?", ".f");
+    basic_test("167772161 167772160 184549375 ip.int-contains?", ".t");
+    basic_error_test("abc ip.to-range-ints", "1:5: ip.to-range-ints argument must be ip object");
+}
+
+#[test]
+fn ip_sort_test() {
+    basic_test(
+        "(10.0.0.10 10.0.0.2 ::1 10.0.0.0/24 10.0.0.0/8 1.0.0.0-1.0.0.5) [ip] map; ip.sort; [str] map; take-all; , join",
+        "1.0.0.0-1.0.0.5,10.0.0.0/8,10.0.0.0/24,10.0.0.2,10.0.0.10,::1",
+    );
+    basic_test("() ip.sort; len", "0");
+    basic_error_test("(1 2) ip.sort", "1:8: ip.sort argument must contain ip objects");
+}
+
+#[test]
+fn dedup_ips_test() {
+    basic_test("(1.2.3.4 1.2.3.4 1.2.3.5) .f dedup-ips; str", "1.2.3.4/31");
+    basic_test("(1.2.3.4 ::1 1.2.3.4 0::1) .t dedup-ips; keys; , join", "1.2.3.4,::1");
+    basic_test("(1.2.3.4 ::1 1.2.3.4/32 1.2.3.4) [ip] map; .t dedup-ips; 1.2.3.4 get", "3");
+    basic_error_test(
+        "(abc) .t dedup-ips",
+        "1:11: ip argument must be valid IP address string: invalid IP address syntax",
+    );
+}
+
 #[test]
 fn ipset_test() {
     basic_test(
@@ -1044,6 +1590,18 @@ fn predicate_test() {
     basic_test("\"1.0\" is-float;", ".f");
 }
 
+#[test]
+fn assert_type_test() {
+    basic_test("1 int assert-type;", "1");
+    basic_test("asdf (int str) assert-type;", "asdf");
+    basic_test("(1 2) list assert-type; len;", "2");
+    basic_error_test("1 str assert-type;", "1:7: assert-type: expected str, got int");
+    basic_error_test(
+        "h(1 2) (list set) assert-type;",
+        "1:21: assert-type: expected list or set, got hash",
+    );
+}
+
 #[test]
 fn bigint_conversion_test() {
     basic_test("1 bigint;", "1");
@@ -1054,6 +1612,183 @@ fn bigint_conversion_test() {
     basic_test("asdf bigint;", "null");
 }
 
+#[test]
+fn bytes_test() {
+    basic_test("asdf to-bytes; len;", "4");
+    basic_test("é to-bytes; 1 get;", "0xa9");
+    basic_test("héllo to-bytes; .f from-bytes;", "héllo");
+    basic_test("(104 255 105) [byte] map; r; .t from-bytes;", "h\u{FFFD}i");
+    basic_error_test(
+        "(104 255 105) [byte] map; r; .f from-bytes;",
+        "1:35: from-bytes argument is not valid UTF-8 (invalid byte at offset 1)",
+    );
+    basic_error_test("(1 2) .f from-bytes;", "1:11: from-bytes list must contain bytes");
+}
+
+#[test]
+fn base64_test() {
+    basic_test("hello to-base64;", "aGVsbG8=");
+    basic_test("\"hi?>\" to-base64;", "aGk/Pg==");
+    basic_test("\"hi?>\" to-base64url;", "aGk_Pg");
+    basic_test("(255 254) [byte] map; r; to-base64url;", "__4");
+    basic_test("aGVsbG8 from-base64; .f from-bytes;", "hello");
+    basic_test("aGk_Pg from-base64; .f from-bytes;", "hi?>");
+    basic_test("aGk/Pg== from-base64; .f from-bytes;", "hi?>");
+    basic_test("aGk_Pg== from-base64url; .f from-bytes;", "hi?>");
+    basic_error_test("aGk/Pg from-base64url;", "1:8: from-base64url argument is not valid base64");
+    basic_error_test("a!b from-base64;", "1:5: from-base64 argument is not valid base64");
+}
+
+#[test]
+fn ascii_utf8_test() {
+    basic_test("abc ascii?;", ".t");
+    basic_test("é ascii?;", ".f");
+    basic_test("é to-bytes; valid-utf8?;", ".t");
+    basic_test("(104 255 105) [byte] map; r; valid-utf8?;", ".f");
+    basic_error_test("(1 2) valid-utf8?;", "1:8: valid-utf8? list must contain bytes");
+    basic_error_test("3 valid-utf8?;", "1:3: valid-utf8? argument must be byte list");
+}
+
+#[test]
+fn ordinal_test() {
+    basic_test("(1 2 3 4) [ordinal] map; , join;", "1st,2nd,3rd,4th");
+    basic_test("(11 12 13) [ordinal] map; , join;", "11th,12th,13th");
+    basic_test("(111 121 1012) [ordinal] map; , join;", "111th,121st,1012th");
+    basic_test("(-1 -11 -23) [ordinal] map; , join;", "-1st,-11th,-23rd");
+    basic_test("0 ordinal;", "0th");
+    basic_error_test("1.5 ordinal;", "1:5: ordinal argument must be integer");
+    basic_test("0 number-words;", "zero");
+    basic_test("42 number-words;", "forty-two");
+    basic_test("-13 number-words;", "\"negative thirteen\"");
+    basic_test(
+        "1234567 number-words;",
+        "\"one million two hundred thirty-four thousand five hundred sixty-seven\"",
+    );
+}
+
+#[test]
+fn uri_encode_test() {
+    basic_test("\"a b/c?d=é&x\" path uri-encode;", "a%20b%2Fc%3Fd=%C3%A9&x");
+    basic_test("\"a b/c?d=é&x\" query uri-encode;", "a%20b/c?d%3D%C3%A9%26x");
+    basic_test("\"a b/c?d=é&x\" full uri-encode;", "a%20b/c?d=%C3%A9&x");
+    basic_test("a%20b%2Fc%C3%A9+ uri-decode;", "\"a b/cé+\"");
+    basic_error_test("a%2 uri-decode;", "1:5: uri-decode argument is not validly encoded");
+    basic_error_test("a%ff uri-decode;", "1:6: uri-decode argument is not validly encoded");
+    basic_error_test(
+        "abc x uri-encode;",
+        "1:7: second uri-encode argument must be 'path', 'query', or 'full'",
+    );
+}
+
+#[test]
+fn human_size_test() {
+    basic_test("1610612736 1024 1 human-size;", "1.5G");
+    basic_test("1500000000 1000 1 human-size;", "1.5G");
+    basic_test("512 1024 1 human-size;", "512");
+    basic_test("1048575 1024 1 human-size;", "1.0M");
+    basic_test("-2048 1024 0 human-size;", "-2K");
+    basic_test("2M 1024 parse-size;", "2097152");
+    basic_test("2M 1000 parse-size;", "2000000");
+    basic_test("\"1.5 GiB\" 1000 parse-size;", "1610612736");
+    basic_test("8E 1024 parse-size;", "9223372036854775808");
+    basic_error_test(
+        "2M 999 parse-size;",
+        "1:8: second parse-size argument must be 1000 or 1024",
+    );
+    basic_error_test(
+        "asdf 1000 parse-size;",
+        "1:11: first parse-size argument is not a valid size",
+    );
+    basic_error_test(
+        &format!("{}.5K 1000 parse-size;", "9".repeat(400)),
+        "1:410: first parse-size argument is too large",
+    );
+}
+
+#[test]
+fn casefold_test() {
+    basic_test("Straße casefold;", "strasse");
+    basic_test("Straße casefold; STRASSE casefold; =;", ".t");
+    basic_test("Straße lc; STRASSE lc; =;", ".f");
+    basic_test("ΣΊΣΥΦΟΣ casefold; σίσυφος casefold; =;", ".t");
+    basic_test("ﬁle casefold;", "file");
+    basic_test("ΣΑΣ casefold;", "σασ");
+    basic_test("ı casefold;", "ı");
+}
+
+#[test]
+fn slugify_test() {
+    basic_test("\"Hello, World!\" slugify;", "hello-world");
+    basic_test("\"  Crème Brûlée -- Straße  \" slugify;", "creme-brulee-strasse");
+    basic_test("\"日本語 title\" slugify;", "title");
+    basic_test("\"Hello, World! Again\" _ 12 slugify-with;", "hello_world");
+    basic_test("\"a b c\" . 0 slugify-with;", "a.b.c");
+}
+
+#[test]
+fn display_width_test() {
+    basic_test("abc display-width;", "3");
+    basic_test("日本語 display-width;", "6");
+    basic_test("e 769 chr; ++; display-width;", "1");
+    basic_test(
+        "27 chr; \"[31mred\" ++; 27 chr; ++; \"[0m\" ++; display-width;",
+        "3",
+    );
+    basic_test("\"\" display-width;", "0");
+}
+
+#[test]
+fn truncate_test() {
+    basic_test("\"hello world\" 8 truncate;", "\"hello w…\"");
+    basic_test("hello 5 truncate;", "hello");
+    basic_test("日本語テキスト 7 truncate;", "日本語…");
+    basic_test("e 769 chr; ++; \"eeee\" ++; 3 truncate; display-width;", "3");
+    basic_test("\"hello world\" ... 8 truncate-with;", "hello...");
+    basic_test("\"hello world\" ... 2 truncate-with;", "he");
+    basic_error_test(
+        "hello -1 truncate;",
+        "1:10: second truncate argument must be non-negative integer",
+    );
+}
+
+#[test]
+fn newlines_test() {
+    basic_test("\"a\\r\\nb\\nc\\r\\n\" to-unix-newlines;", "\"a\\nb\\nc\\n\"");
+    basic_test("\"a\\r\\nb\\nc\" to-dos-newlines;", "\"a\\r\\nb\\r\\nc\"");
+    basic_test(
+        "\"a\\nb\" to-dos-newlines; to-dos-newlines; to-unix-newlines;",
+        "\"a\\nb\"",
+    );
+}
+
+#[test]
+fn glob_match_test() {
+    basic_test("www.example.com \"*.example.com\" glob-match?;", ".t");
+    basic_test("example.com \"*.example.com\" glob-match?;", ".f");
+    basic_test("abc \"a?c\" glob-match?;", ".t");
+    basic_test("abc \"a[a-c]c\" glob-match?;", ".t");
+    basic_test("abc \"a[!a-c]c\" glob-match?;", ".f");
+    basic_test("\"a]c\" \"a[]]c\" glob-match?;", ".t");
+    basic_test("abc \"a\\\\*c\" glob-match?;", ".f");
+    basic_test("aaaab \"a*a*b\" glob-match?;", ".t");
+    basic_test("WWW.EXAMPLE.COM \"*.example.com\" glob-match?;", ".f");
+    basic_test("WWW.EXAMPLE.COM \"*.example.com\" glob-match-ci?;", ".t");
+}
+
+#[test]
+fn matches_any_test() {
+    basic_test("asdf (qw as) starts-with-any;", ".t");
+    basic_test("asdf (qw zx) starts-with-any;", ".f");
+    basic_test("asdf () starts-with-any;", ".f");
+    basic_test("asdf (\"^q\" \"sd\") matches-any;", ".t");
+    basic_test("asdf (\"^q\" x) matches-any;", ".f");
+    basic_test("asdf (\"^q\" \"SD/i\") matches-any;", ".t");
+    basic_test(
+        "(asdf qwer zxcv) [(as zx) starts-with-any] grep; r;",
+        "(\n    0: asdf\n    1: zxcv\n)",
+    );
+}
+
 #[test]
 fn chr_test() {
     basic_test("100 chr;", "d");
@@ -1140,6 +1875,23 @@ fn abs_test() {
     basic_test("-10000000000000 abs;", "10000000000000");
 }
 
+#[test]
+fn round_to_test() {
+    basic_test("17 5 round-to;", "15");
+    basic_test("18 5 round-to;", "20");
+    basic_test("25 10 round-to;", "30");
+    basic_test("-25 10 round-to;", "-30");
+    basic_test("17 5 floor-to;", "15");
+    basic_test("-17 5 floor-to;", "-20");
+    basic_test("17 5 ceil-to;", "20");
+    basic_test("15 5 ceil-to;", "15");
+    basic_test("-17 5 ceil-to;", "-15");
+    basic_test("7.3 0.5 round-to;", "7.5");
+    basic_test("10000000000007 10 floor-to;", "10000000000000");
+    basic_error_test("5 0 round-to;", "1:5: round-to multiple cannot be zero");
+    basic_error_test("abc 5 floor-to;", "1:7: first floor-to argument must be number");
+}
+
 #[test]
 fn len_test() {
     basic_test("asdf len;", "4");
@@ -1188,6 +1940,37 @@ fn stat_test() {
     basic_test("{rm -f asdf}; take-all; drop; {rm -f temp}; take-all; drop; Cargo.toml temp cp; {ln -s temp asdf}; take-all; drop; asdf stat; size get; 500 >; asdf lstat; size get; 100 <; and; {rm -f asdf}; take-all; drop; {rm -f temp}; take-all; drop;", ".t");
 }
 
+#[test]
+fn id_name_test() {
+    basic_test("0 uid-name;", "root");
+    basic_test("0 gid-name;", "root");
+    basic_test("root name-uid;", "0");
+    basic_test("root name-gid;", "0");
+    basic_test("nosuchuser name-uid; is-null;", ".t");
+    basic_test("root name-uid; uid-name;", "root");
+    basic_error_test("-1 uid-name;", "1:4: uid-name argument must be non-negative integer");
+}
+
+#[test]
+fn umask_test() {
+    basic_test("\"077\" unoct; set-umask; drop; umask; oct;", "77");
+    basic_test("63 set-umask; drop; 18 set-umask;", "63");
+    basic_error_test("1000 set-umask;", "1:6: set-umask argument must be integer between 0 and 511");
+}
+
+#[test]
+fn uname_test() {
+    basic_test("hostname; len; 0 >;", ".t");
+    basic_test("uname; keys; take-all; \",\" join;", "os,os-version,kernel-version,arch");
+}
+
+#[test]
+fn loadavg_meminfo_test() {
+    basic_test("loadavg; len;", "3");
+    basic_test("meminfo; total get; 0 >;", ".t");
+    basic_test("meminfo; dup; total get; swap; available get; >=;", ".t");
+}
+
 #[test]
 fn mv_test() {
     basic_test("
@@ -1395,6 +2178,31 @@ fn slide_test() {
     );
 }
 
+#[test]
+fn chunk_while_test() {
+    basic_test(
+        "(1 2 4 5 6) [swap; 1 +; =] chunk-while; take-all; [len] map; take-all;",
+        "(\n    0: 2\n    1: 3\n)",
+    );
+    basic_test(
+        "(3 1 2 5 4) [<] chunk-while; take-all; [\"\" join] map; take-all;",
+        "(\n    0: 3\n    1: 125\n    2: 4\n)",
+    );
+    basic_test("() [<] chunk-while; take-all;", "()");
+}
+
+#[test]
+fn number_lines_test() {
+    basic_test(
+        "(a \"\" b) number-lines; take-all;",
+        "(\n    0: \"     1\\ta\"\n    1: \"     2\\t\"\n    2: \"     3\\tb\"\n)",
+    );
+    basic_test(
+        "(a \"\" b) 9 3 .f number-lines-with; take-all;",
+        "(\n    0: \"  9\\ta\"\n    1: \"\"\n    2: \" 10\\tb\"\n)",
+    );
+}
+
 #[test]
 fn before_test() {
     basic_test(
@@ -1628,6 +2436,45 @@ fn invalid_strftime_test() {
                      "1:15: second strftime argument is invalid");
 }
 
+#[test]
+fn duration_test() {
+    basic_test("5025 duration-format;", "1h23m45s");
+    basic_test("0 duration-format;", "0s");
+    basic_test("-90 duration-format;", "-1m30s");
+    basic_test("90061.25 duration-format;", "1d1h1m1.25s");
+    basic_test("3.0000000000000004 duration-format;", "3s");
+    basic_test("59.9999999999 duration-format;", "1m");
+    basic_test("0.0000000001 duration-format;", "0s");
+    basic_test("1h30m parse-duration;", "5400");
+    basic_test("-1m30s parse-duration;", "-90");
+    basic_test("1s500ms parse-duration;", "1.5");
+    basic_error_test("1x parse-duration;",
+                     "1:4: parse-duration argument is not a valid duration");
+}
+
+#[test]
+fn load_env_test() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "# comment\nexport A=1\nB = \"two words\" # trailing\nC='single $x'\nD=plain value # comment\n\nE=").unwrap();
+    let path = file.path().to_str().unwrap();
+    basic_test(
+        &format!("{} .f load-env;", path),
+        "h(\n    \"A\": 1\n    \"B\": \"two words\"\n    \"C\": \"single $x\"\n    \"D\": \"plain value\"\n    \"E\": \"\"\n)",
+    );
+    basic_test(
+        &format!("{} .t load-env; D getenv;", path),
+        "\"plain value\"",
+    );
+
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, "A=1\nbad line").unwrap();
+    let path = file.path().to_str().unwrap();
+    basic_error_test(
+        &format!("{} .f load-env;", path),
+        &format!("1:{}: load-env file has invalid syntax at line 2", path.len() + 5),
+    );
+}
+
 #[test]
 fn env_scope_test() {
     basic_test("env; TEST_VAR get; {TEST_VAR=1234 true}; r; env; TEST_VAR get",
@@ -1852,6 +2699,36 @@ fn ss_test() {
     basic_test("1 2 3 4 .ss; clear;", "4");
 }
 
+#[test]
+fn debug_stack_test() {
+    basic_error_test(
+        "1 (2 h(a 3)) 3 range; .d; clear;",
+        "0: int 1\n1: list (\n    0: int 2\n    1: hash (\n        \"a\": int 3\n    )\n)\n2: gen ((Generator))"
+    );
+    basic_test("1 2 .d; +;", "3");
+}
+
+#[test]
+fn profile_test() {
+    let mut file = NamedTempFile::new().unwrap();
+    writeln!(file, ": inc 1 +; ,, : twice inc; inc; ,, [0 twice; twice] profile;").unwrap();
+
+    let mut cmd = Command::cargo_bin("cosh").unwrap();
+    let path = file.path();
+    let output = cmd.arg("--no-cosh-conf").arg(path).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "4\n");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let counts: Vec<(&str, &str)> =
+        stderr.lines()
+              .map(|l| l.split_whitespace().collect::<Vec<&str>>())
+              .filter(|p| p.len() == 3)
+              .map(|p| (p[0], p[2]))
+              .collect();
+    assert!(counts.contains(&("4", "inc")));
+    assert!(counts.contains(&("2", "twice")));
+}
+
 #[test]
 fn long_json_test() {
     basic_test("test-data/long.json f<; from-json; asdf get; len", "4012");
@@ -1880,6 +2757,21 @@ fn digest_test() {
     basic_test("password sha512; hex", "b109f3bbbc244eb82441917ed06d618b9008dd09b3befd1b5e07394c706a8bb980b1d7785e5976ec049b46df5f1326af5a2ea6d103fd07c95385ffab0cacbc86");
 }
 
+#[test]
+fn fingerprint_test() {
+    basic_test(
+        "(1 2 3) fingerprint;",
+        "6f2bc448ae2799665148501f2705df543aadc21d19702fb0ccd4fe6b36e3d83c",
+    );
+    basic_test("(1 (2 3)) fingerprint; (1 (2 3)) fingerprint; =;", ".t");
+    basic_test("10000000000000 9999999999999 -; fingerprint; 1 fingerprint; =;", ".t");
+    basic_test("h(a 1 b 2) fingerprint; h(b 2 a 1) fingerprint; =;", ".t");
+    basic_test("s(1 2) fingerprint; s(2 1) fingerprint; =;", ".t");
+    basic_test("(1 2) fingerprint; (\"1\" \"2\") fingerprint; =;", ".f");
+    basic_test("1.0 fingerprint; 1 fingerprint; =;", ".f");
+    basic_error_test("[1] fingerprint;", "1:6: fingerprint argument cannot be fingerprinted");
+}
+
 #[test]
 fn jobs_test() {
     basic_test("{sleep 2}; n var; n !; jobs; 0.complete get; jobs; 0.pid get; term kill", ".f");
@@ -1887,6 +2779,26 @@ fn jobs_test() {
     basic_test("(1 2) [2 sleep] 2 pmapn; n var; n !; jobs; len; 1 =; 3 sleep; jobs; len; 1 =; jobs; len; 0 =; and; and;", ".t");
 }
 
+#[test]
+fn kill_test() {
+    basic_test("{sleep 2}; n var; n !; jobs; 0.pid get; SIGTERM kill; 0.1 sleep; jobs; 0.complete get;", ".t");
+    basic_test("{sleep 2}; n var; n !; jobs; 0.pid get; 15 kill; 0.1 sleep; jobs; 0.complete get;", ".t");
+    basic_error_test("999999 TERM kill;", "1:13: unable to kill process 999999: ESRCH: No such process");
+    basic_error_test("999999 SIGFOO kill;", "1:15: invalid signal");
+    basic_error_test("999999 hup killpg;", "1:12: unable to kill process group 999999: ESRCH: No such process");
+}
+
+#[test]
+fn wait_test() {
+    basic_test("{sh -c \"sleep 0.2; echo hi; exit 3\"}; dup; wait; swap; take-all;",
+               "3\n(\n    0: \"hi\\n\"\n)");
+    basic_test("{seq 1 100000}; dup; wait; drop; take-all; len;", "100000");
+    basic_test("{sleep 0.2}; n var; n !; jobs; 0.pid get; wait;", "0");
+    basic_test("{sleep 5}; n var; n !; jobs; 0.pid get; kill kill; n @; wait;", "137");
+    basic_test("{sleep 0.2}; x var; x !; {sleep 0.3}; y var; y !; wait-all; jobs; len;", "0");
+    basic_error_test("a wait;", "1:3: wait argument must be command generator or process ID");
+}
+
 #[test]
 fn status_test() {
     basic_test("{ls}; dup; r; drop; 0.1 sleep; status", "0");